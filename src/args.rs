@@ -3,6 +3,14 @@ use clap::{
     Parser,
     Subcommand
 };
+use ruscan::inventory::{ColumnMap, InventorySource};
+use ruscan::network::AddressPolicy;
+use ruscan::profile::ScanProfile;
+use ruscan::rate::parse_rate;
+use ruscan::timing::{parse_duration, TimingTemplate};
+use std::ffi::OsString;
+use std::str::FromStr;
+use std::time::Duration;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about)]
@@ -22,7 +30,10 @@ pub enum EntityType {
 #[derive(Debug, Args)]
 #[command(arg_required_else_help = true)] 
 pub struct PortScan {
-    /// IP address of the target network (IPv4 or IPv6). Example: ruscan ps -i 127.0.0.1
+    /// Targets in nmap syntax: IPs, CIDRs, ranges and octet patterns, space or comma separated. Example: ruscan ps 192.168.1-3.1-254 10.0.0,2,4.*
+    #[arg(value_name = "TARGETS")]
+    pub targets: Vec<String>,
+    /// IP address of the target network (IPv4 or IPv6, with a zone ID for link-local addresses). Example: ruscan ps -i fe80::1%eth0
     #[arg(short, long)]
     pub ip: Option<String>,
     /// Domain name of the target network. Example: ruscan ps -d example.com
@@ -31,18 +42,60 @@ pub struct PortScan {
     /// CIDR notation for network scanning (supports IPv4 and IPv6). Example: ruscan ps -c 192.168.1.0/24
     #[arg(short = 'c', long)]
     pub cidr: Option<String>,
-    /// IP range for scanning (start-end format). Example: ruscan ps -R 192.168.1.1-192.168.1.254
+    /// IP range for scanning, as start-end or an nmap octet pattern. Example: ruscan ps -R 192.168.1.1-192.168.1.254 or -R 192.168.1-3.1-254
     #[arg(short = 'R', long)]
     pub ip_range: Option<String>,
-    /// Single port to be scanned on the target network. Example: ruscan ps -i 127.0.0.1 -p 443
-    #[arg(short, long)]
-    pub port: Option<u16>,
-    /// Range of ports to be scanned on the target network. Example: ruscan ps -i 127.0.0.1 -r 1-65535
-    #[arg(short, long)]
+    /// File listing IPs, hostnames, CIDRs, ranges and host:port entries, one per line; `-` reads stdin. Example: ruscan ps -iL targets.txt
+    #[arg(long = "input-list", value_name = "FILE")]
+    pub input_list: Option<String>,
+    /// Import targets and asset tags from an inventory file given as FORMAT:PATH, FORMAT being terraform, ansible, aws, csv or json; repeatable. Example: ruscan ps --inventory terraform:terraform.tfstate
+    #[arg(long, value_name = "FORMAT:PATH", value_parser = InventorySource::from_str)]
+    pub inventory: Vec<InventorySource>,
+    /// Columns of csv and json inventories as address=COLUMN, hostname=COLUMN and TAG=COLUMN entries; without TAG entries every other column becomes a tag. Example: --inventory-map address=ip,owner=team
+    #[arg(long, value_name = "MAP", value_parser = ColumnMap::from_str)]
+    pub inventory_map: Option<ColumnMap>,
+    /// Scan the subnets attached to this machine's network interfaces (loopback excluded). Example: ruscan ps --local-subnets
+    #[arg(long)]
+    pub local_subnets: bool,
+    /// Scan the subnets attached to one network interface, loopback included if named. Example: ruscan ps --interface eth0
+    #[arg(long, value_name = "NAME")]
+    pub interface: Option<String>,
+    /// Ports to scan: numbers, ranges, `-` for all, service names and T:/U: prefixes. Example: ruscan ps -i 127.0.0.1 -p 22,80,8000-8100,https
+    #[arg(short, long, allow_hyphen_values = true)]
+    pub port: Option<String>,
+    /// Range of ports to be scanned, same syntax as -p. Example: ruscan ps -i 127.0.0.1 -r 1-65535
+    #[arg(short, long, allow_hyphen_values = true)]
     pub range: Option<String>,
-    /// List of ports to be scanned on the target network (comma-separated). Example: ruscan ps -i 127.0.0.1 -l 21,80,443
-    #[arg(short, long)]
+    /// List of ports to be scanned, same syntax as -p. Example: ruscan ps -i 127.0.0.1 -l 21,80,443
+    #[arg(short, long, allow_hyphen_values = true)]
     pub list: Option<String>,
+    /// Scan the N ports most frequently found open according to the bundled service table, on top of -p/-r/-l. Example: ruscan ps -c 10.0.0.0/24 --top-ports 100
+    #[arg(long, value_name = "N")]
+    pub top_ports: Option<usize>,
+    /// IPs, CIDRs or ranges never to connect to (comma-separated). Example: ruscan ps -c 10.0.0.0/24 --exclude 10.0.0.1,10.0.0.128/25
+    #[arg(long)]
+    pub exclude: Option<String>,
+    /// File of IPs, CIDRs or ranges never to connect to, one or more per line, `#` comments allowed
+    #[arg(long)]
+    pub exclude_file: Option<String>,
+    /// Ports never to connect to on any target, same syntax as -p. Example: --exclude-ports 9100,6000-6010
+    #[arg(long, allow_hyphen_values = true)]
+    pub exclude_ports: Option<String>,
+    /// Scope file of allowed targets and domains, forbidden ranges and allowed time windows; targets outside it are never connected to. Example: ruscan ps -c 10.0.0.0/16 --scope engagement.scope
+    #[arg(long, value_name = "FILE")]
+    pub scope: Option<String>,
+    /// What to do with special targets, as CLASS=ACTION entries over the defaults; classes are network (.0 and .255 from ranges and patterns), multicast, reserved, documentation, loopback and link-local, actions skip, warn or allow. The first four are skipped by default, the others scanned with a warning. Example: --special-addresses network=allow,loopback=skip
+    #[arg(long, value_name = "POLICY", value_parser = AddressPolicy::from_str)]
+    pub special_addresses: Option<AddressPolicy>,
+    /// Ask for confirmation before sending more than this many probes
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    pub confirm_above: u128,
+    /// Start without asking, even for large scans or public addresses (for automation)
+    #[arg(short = 'y', long)]
+    pub yes: bool,
+    /// Expand targets, ports and exclusions and print the scan plan with a time estimate, without connecting anywhere; -o json emits it as JSON. Example: ruscan ps -c 10.0.0.0/16 --top-ports 100 --dry-run
+    #[arg(long)]
+    pub dry_run: bool,
     /// Output format: json, csv, html, or text (default). Example: ruscan ps -i 127.0.0.1 -o json
     #[arg(short, long, default_value = "text")]
     pub output: String,
@@ -52,12 +105,68 @@ pub struct PortScan {
     /// Enable service and version detection (banner grabbing). Example: ruscan ps -i 127.0.0.1 -s
     #[arg(short = 's', long)]
     pub service_detection: bool,
-    /// Maximum number of concurrent connections for rate control (default: 1000). Example: ruscan ps -i 127.0.0.1 -t 100
-    #[arg(short = 't', long, default_value = "1000")]
-    pub threads: usize,
+    /// Checkpoint progress to a state file and resume from it if it exists; the targets and ports must be unchanged. Example: ruscan ps -c 10.0.0.0/8 --resume scan.state
+    #[arg(long)]
+    pub resume: Option<String>,
+    /// Timing template 0-5 (paranoid, sneaky, polite, normal, aggressive, insane) setting concurrency, timeouts, retries and delay together (default: 3). Example: ruscan ps -i 127.0.0.1 -T4
+    #[arg(short = 'T', long = "timing", value_parser = TimingTemplate::from_str)]
+    pub timing: Option<TimingTemplate>,
+    /// Scan profile bundling ports, service detection and timing: quick, full, web or stealthy. Example: ruscan ps -i 127.0.0.1 --profile web
+    #[arg(long, value_parser = ScanProfile::from_str)]
+    pub profile: Option<ScanProfile>,
+    /// Maximum number of concurrent connections, overriding the timing template (normal: 1000). Example: ruscan ps -i 127.0.0.1 -t 100
+    #[arg(short = 't', long)]
+    pub threads: Option<usize>,
+    /// Timeout for each connection attempt (ms, s, m; bare numbers are ms), overriding the timing template (normal: 3s). Example: ruscan ps -i 127.0.0.1 --connect-timeout 500ms
+    #[arg(long, value_parser = parse_duration)]
+    pub connect_timeout: Option<Duration>,
+    /// Timeout for each read during service detection, overriding the timing template (normal: 2s). Example: ruscan ps -i 127.0.0.1 -s --read-timeout 5s
+    #[arg(long, value_parser = parse_duration)]
+    pub read_timeout: Option<Duration>,
+    /// Number of times to re-probe a port whose connection timed out, overriding the timing template (normal: 0). Example: ruscan ps -c 10.8.0.0/24 --max-retries 2
+    #[arg(long)]
+    pub max_retries: Option<u32>,
+    /// Maximum number of concurrent connections against any single host. Example: ruscan ps -i 10.0.0.5 -r 1-65535 --max-per-host 20
+    #[arg(long)]
+    pub max_per_host: Option<usize>,
+    /// Minimum delay between probes to the same host, overriding the timing template. Example: ruscan ps -i 10.0.0.5 --scan-delay 100ms
+    #[arg(long, value_parser = parse_duration)]
+    pub scan_delay: Option<Duration>,
+    /// Maximum the per-host delay may grow to when a host starts dropping probes. Example: ruscan ps -c 10.0.0.0/24 --max-scan-delay 1s
+    #[arg(long, value_parser = parse_duration)]
+    pub max_scan_delay: Option<Duration>,
+    /// Maximum connection attempts per second across the scan, service detection included. Example: ruscan ps -c 10.0.0.0/24 --max-rate 200
+    #[arg(long, value_parser = parse_rate)]
+    pub max_rate: Option<f64>,
+    /// Minimum connection attempts per second; concurrency is raised when needed to keep up. Example: ruscan ps -c 10.0.0.0/24 --min-rate 500
+    #[arg(long, value_parser = parse_rate)]
+    pub min_rate: Option<f64>,
+    /// Keep concurrency fixed at --threads instead of adapting it to timeouts (AIMD)
+    #[arg(long)]
+    pub fixed_concurrency: bool,
+    /// Scan hosts and ports in ascending order instead of a random permutation
+    #[arg(long)]
+    pub no_randomize: bool,
+    /// Seed for the random scan order, to reproduce an earlier scan's order
+    #[arg(long)]
+    pub seed: Option<u64>,
+    /// Print engine adjustments such as concurrency and per-host delay changes
+    #[arg(short = 'v', long)]
+    pub verbose: bool,
+    /// Adapt connect timeouts per host from measured round-trip times, using --connect-timeout as the upper bound
+    #[arg(long)]
+    pub adaptive_timeout: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct Enumuration{
 
-}
\ No newline at end of file
+}
+
+/// Command line arguments with nmap's two-letter `-iL` rewritten to `--input-list`,
+/// which clap cannot express as a short flag
+pub fn normalized_args() -> Vec<OsString> {
+    std::env::args_os()
+        .map(|arg| if arg == "-iL" { OsString::from("--input-list") } else { arg })
+        .collect()
+}
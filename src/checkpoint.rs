@@ -0,0 +1,186 @@
+use crate::report::{PortState, ScanResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, ErrorKind, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often buffered checkpoint entries are forced out to disk
+pub const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// First line of a state file
+#[derive(Serialize, Deserialize)]
+struct Header {
+    ruscan_checkpoint: u32,
+    fingerprint: String,
+    /// Seed of the random scan order, absent for sequential scans
+    #[serde(default)]
+    seed: Option<u64>,
+}
+
+/// One finished host/port pair; `index` is its position in the scan order
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    index: u64,
+    result: ScanResult,
+}
+
+/// Progress recovered from a state file.
+///
+/// A state file is a JSON-lines journal: a header carrying the fingerprint of the
+/// target and port specification, then one entry per completed pair. Appending
+/// keeps checkpoints cheap on huge scans, and a line torn by a crash is ignored.
+#[derive(Debug)]
+pub struct Checkpoint {
+    fingerprint: String,
+    seed: Option<u64>,
+    /// Every position below this one has completed
+    next_index: u64,
+    /// Completed positions at or above `next_index`
+    completed: HashSet<u64>,
+    results: Vec<ScanResult>,
+}
+
+impl Checkpoint {
+    /// Read a state file; `Ok(None)` when it does not exist yet
+    pub fn load(path: &str) -> Result<Option<Self>, String> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to open state file {}: {}", path, e)),
+        };
+        let mut lines = BufReader::new(file).lines();
+
+        let header: Header = lines.next()
+            .and_then(|line| line.ok())
+            .and_then(|line| serde_json::from_str(&line).ok())
+            .ok_or_else(|| format!("{} is not a ruscan state file", path))?;
+
+        let mut indices = Vec::new();
+        let mut results = Vec::new();
+        for line in lines {
+            let line = line.map_err(|e| format!("Failed to read state file {}: {}", path, e))?;
+            // A crash can leave the last line half written
+            if let Ok(entry) = serde_json::from_str::<Entry>(&line) {
+                indices.push(entry.index);
+                results.push(entry.result);
+            }
+        }
+
+        // Fold the contiguous prefix into a cursor so only the stragglers stay in memory
+        indices.sort_unstable();
+        indices.dedup();
+        let next_index = indices.iter().enumerate()
+            .take_while(|(position, index)| *position as u64 == **index)
+            .count() as u64;
+        let completed = indices.into_iter().filter(|index| *index >= next_index).collect();
+
+        Ok(Some(Checkpoint {
+            fingerprint: header.fingerprint,
+            seed: header.seed,
+            next_index,
+            completed,
+            results,
+        }))
+    }
+
+    /// Fingerprint of the target and port specification the state file belongs to
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+
+    /// Seed of the random scan order the earlier run used, needed to replay it
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    /// Number of host/port pairs already finished
+    pub fn completed(&self) -> u64 {
+        self.next_index + self.completed.len() as u64
+    }
+
+    /// Whether the pair at `index` in the scan order was finished before
+    pub fn is_done(&self, index: u64) -> bool {
+        index < self.next_index || self.completed.contains(&index)
+    }
+
+    /// Hand over the results recorded so far, e.g. to seed a `ReportGenerator`
+    pub fn take_results(&mut self) -> Vec<ScanResult> {
+        std::mem::take(&mut self.results)
+    }
+}
+
+/// Appends completed pairs to a state file, flushing at least every [`CHECKPOINT_INTERVAL`]
+#[derive(Debug)]
+pub struct CheckpointWriter {
+    file: BufWriter<File>,
+    last_flush: Instant,
+}
+
+impl CheckpointWriter {
+    /// Start a new state file, replacing any existing one
+    pub fn create(path: &str, fingerprint: &str, seed: Option<u64>) -> Result<Self, String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create state file {}: {}", path, e))?;
+        let mut writer = CheckpointWriter {
+            file: BufWriter::new(file),
+            last_flush: Instant::now(),
+        };
+        let header = Header {
+            ruscan_checkpoint: 1,
+            fingerprint: fingerprint.to_string(),
+            seed,
+        };
+        writer.write_line(&header)
+            .and_then(|_| writer.flush())
+            .map_err(|e| format!("Failed to write state file {}: {}", path, e))?;
+        Ok(writer)
+    }
+
+    /// Continue an existing state file after [`Checkpoint::load`]
+    pub fn append(path: &str) -> Result<Self, String> {
+        let file = OpenOptions::new().append(true).open(Path::new(path))
+            .map_err(|e| format!("Failed to open state file {}: {}", path, e))?;
+        Ok(CheckpointWriter {
+            file: BufWriter::new(file),
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// Record the outcome of the pair at `index`; pairs never attempted are left out
+    /// so a resumed scan tries them
+    pub fn record(&mut self, index: u64, result: &ScanResult) -> Result<(), String> {
+        if result.status == PortState::NotAttempted {
+            return Ok(());
+        }
+        self.write_line(&Entry { index, result: result.clone() })
+            .map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        if self.last_flush.elapsed() >= CHECKPOINT_INTERVAL {
+            self.flush().map_err(|e| format!("Failed to write checkpoint: {}", e))?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.last_flush = Instant::now();
+        self.file.flush()
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) -> std::io::Result<()> {
+        serde_json::to_writer(&mut self.file, value)?;
+        self.file.write_all(b"\n")
+    }
+}
+
+/// 64-bit FNV-1a, stable across builds unlike `std`'s default hasher
+pub fn fingerprint<'a, I: IntoIterator<Item = &'a [u8]>>(parts: I) -> String {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for part in parts {
+        for byte in part.iter().chain(std::iter::once(&0xff)) {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    format!("{:016x}", hash)
+}
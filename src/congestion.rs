@@ -0,0 +1,107 @@
+/// Share of timeouts in a round above which the network is treated as congested
+pub const DROP_THRESHOLD: f64 = 0.2;
+/// Concurrency window a scan starts from, before it has seen any answers
+pub const INITIAL_WINDOW: usize = 64;
+
+/// AIMD controller for the number of probes kept in flight.
+///
+/// Outcomes are judged in rounds of one window's worth of completed probes. A round
+/// whose timeout share stays under [`DROP_THRESHOLD`] grows the window, doubling
+/// (slow start) until the first congestion event and then by an eighth of the
+/// threshold learned there. A congested round halves the window. Only timeouts on
+/// hosts that have answered before count as drops, so a firewall silently
+/// discarding everything on one host does not throttle the rest of the scan; timeouts
+/// on hosts that never answered still complete the round, so a sweep of mostly
+/// silent addresses keeps growing the window.
+#[derive(Debug, Clone)]
+pub struct CongestionController {
+    window: usize,
+    max: usize,
+    ssthresh: Option<usize>,
+    /// Smallest window allowed, raised by --min-rate when every slot stays busy
+    floor: usize,
+    answered: u64,
+    dropped: u64,
+    silent: u64,
+}
+
+/// Window change made at the end of a round
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustment {
+    pub from: usize,
+    pub to: usize,
+    pub drop_ratio: f64,
+}
+
+impl CongestionController {
+    pub fn new(max: usize) -> Self {
+        CongestionController {
+            window: INITIAL_WINDOW.min(max).max(1),
+            max: max.max(1),
+            ssthresh: None,
+            floor: 1,
+            answered: 0,
+            dropped: 0,
+            silent: 0,
+        }
+    }
+
+    /// Number of probes currently allowed in flight
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// The probe got an answer (handshake, reset or unreachable)
+    pub fn record_answer(&mut self) -> Option<Adjustment> {
+        self.answered += 1;
+        self.end_round()
+    }
+
+    /// The probe timed out against a host known to be responsive
+    pub fn record_drop(&mut self) -> Option<Adjustment> {
+        self.dropped += 1;
+        self.end_round()
+    }
+
+    /// The probe timed out against a host that has never answered
+    pub fn record_silence(&mut self) -> Option<Adjustment> {
+        self.silent += 1;
+        self.end_round()
+    }
+
+    /// Never shrink the window below `floor`, growing it and the maximum to match
+    pub fn raise_floor(&mut self, floor: usize) {
+        self.floor = self.floor.max(floor);
+        self.max = self.max.max(self.floor);
+        self.window = self.window.max(self.floor);
+    }
+
+    fn end_round(&mut self) -> Option<Adjustment> {
+        let total = self.answered + self.dropped + self.silent;
+        if total < self.window as u64 {
+            return None;
+        }
+        let drop_ratio = self.dropped as f64 / total as f64;
+        self.answered = 0;
+        self.dropped = 0;
+        self.silent = 0;
+
+        let from = self.window;
+        if drop_ratio > DROP_THRESHOLD {
+            let halved = (self.window / 2).max(self.floor);
+            self.ssthresh = Some(halved);
+            self.window = halved;
+        } else {
+            self.window = match self.ssthresh {
+                Some(ssthresh) if self.window >= ssthresh => self.window + (ssthresh / 8).max(1),
+                _ => self.window * 2,
+            }
+            .min(self.max);
+        }
+        (self.window != from).then_some(Adjustment {
+            from,
+            to: self.window,
+            drop_ratio,
+        })
+    }
+}
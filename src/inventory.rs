@@ -0,0 +1,527 @@
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// Attribute names holding host addresses in Terraform resources (AWS, GCP, Azure,
+/// OpenStack, DigitalOcean, Hetzner and similar providers)
+const TERRAFORM_ADDRESS_KEYS: [&str; 15] = [
+    "private_ip",
+    "private_ips",
+    "public_ip",
+    "ipv4_address",
+    "ipv4_address_private",
+    "ipv6_address",
+    "ipv6_addresses",
+    "access_ip_v4",
+    "access_ip_v6",
+    "network_ip",
+    "nat_ip",
+    "private_ip_address",
+    "public_ip_address",
+    "ip_address",
+    "fixed_ip_v4",
+];
+
+/// Columns tried, in order, for addresses and hostnames when no mapping names them
+const DEFAULT_ADDRESS_COLUMNS: [&str; 5] = ["address", "ip", "ip_address", "private_ip", "ipv4"];
+const DEFAULT_HOSTNAME_COLUMNS: [&str; 4] = ["hostname", "fqdn", "dns_name", "host"];
+
+/// Most hosts one Ansible range pattern such as `web[01:20]` may expand to
+pub const MAX_HOST_RANGE: u32 = 65536;
+
+/// Kind of inventory file an importer understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryFormat {
+    /// Terraform state, or the output of `terraform show -json`
+    Terraform,
+    /// Ansible INI inventory, or the output of `ansible-inventory --list`
+    Ansible,
+    /// Output of `aws ec2 describe-instances`
+    Aws,
+    /// CSV with a header row, columns picked by a [`ColumnMap`]
+    Csv,
+    /// Array of JSON objects, fields picked by a [`ColumnMap`]
+    Json,
+}
+
+impl InventoryFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            InventoryFormat::Terraform => "terraform",
+            InventoryFormat::Ansible => "ansible",
+            InventoryFormat::Aws => "aws",
+            InventoryFormat::Csv => "csv",
+            InventoryFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for InventoryFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "terraform" | "tfstate" => Ok(InventoryFormat::Terraform),
+            "ansible" => Ok(InventoryFormat::Ansible),
+            "aws" => Ok(InventoryFormat::Aws),
+            "csv" => Ok(InventoryFormat::Csv),
+            "json" => Ok(InventoryFormat::Json),
+            _ => Err(format!("Unknown inventory format: {} (use terraform, ansible, aws, csv or json)", value)),
+        }
+    }
+}
+
+/// An inventory file to import, given as `FORMAT:PATH`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventorySource {
+    pub format: InventoryFormat,
+    pub path: String,
+}
+
+impl FromStr for InventorySource {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let (format, path) = value.split_once(':')
+            .filter(|(_, path)| !path.is_empty())
+            .ok_or_else(|| format!("Invalid inventory {}: expected FORMAT:PATH, e.g. terraform:terraform.tfstate", value))?;
+        Ok(InventorySource { format: format.parse()?, path: path.to_string() })
+    }
+}
+
+/// One host found in an inventory, with the tags tying it back to its owner
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Asset {
+    pub address: Option<IpAddr>,
+    /// Name to resolve when there is no address, or to report alongside it
+    pub hostname: Option<String>,
+    /// e.g. `instance_id`, `group` or `resource`
+    pub tags: BTreeMap<String, String>,
+}
+
+/// Which CSV columns or JSON fields of a generic inventory hold what.
+///
+/// Written as `address=COLUMN,hostname=COLUMN,TAG=COLUMN,...`; JSON fields may be
+/// dotted paths such as `tags.owner`. Without any `TAG=COLUMN` entry every other
+/// non-empty column becomes a tag named after it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMap {
+    address: Option<String>,
+    hostname: Option<String>,
+    tags: Vec<(String, String)>,
+}
+
+impl FromStr for ColumnMap {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut map = ColumnMap::default();
+        for item in value.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (key, column) = item.split_once('=')
+                .map(|(key, column)| (key.trim(), column.trim()))
+                .filter(|(key, column)| !key.is_empty() && !column.is_empty())
+                .ok_or_else(|| format!("Invalid column mapping {}: expected NAME=COLUMN", item))?;
+            match key {
+                "address" => map.address = Some(column.to_string()),
+                "hostname" => map.hostname = Some(column.to_string()),
+                tag => map.tags.push((tag.to_string(), column.to_string())),
+            }
+        }
+        Ok(map)
+    }
+}
+
+impl ColumnMap {
+    /// Address, hostname and tags of one record, given a lookup of its fields
+    fn asset(&self, fields: &[&str], get: impl Fn(&str) -> Option<String>) -> Asset {
+        let is_column = |chosen: &Option<String>, defaults: &[&str], field: &str| match chosen {
+            Some(column) => column.eq_ignore_ascii_case(field),
+            None => defaults.iter().any(|column| column.eq_ignore_ascii_case(field)),
+        };
+        let pick = |chosen: &Option<String>, defaults: &[&str]| match chosen {
+            Some(column) => get(column),
+            None => defaults.iter().find_map(|column| get(column)),
+        };
+        let address = pick(&self.address, &DEFAULT_ADDRESS_COLUMNS);
+        let hostname = pick(&self.hostname, &DEFAULT_HOSTNAME_COLUMNS);
+        let tags = if self.tags.is_empty() {
+            fields.iter()
+                .filter(|field| !is_column(&self.address, &DEFAULT_ADDRESS_COLUMNS, field))
+                .filter(|field| !is_column(&self.hostname, &DEFAULT_HOSTNAME_COLUMNS, field))
+                .filter_map(|field| get(field).map(|value| (field.to_string(), value)))
+                .collect()
+        } else {
+            self.tags.iter()
+                .filter_map(|(tag, column)| get(column).map(|value| (tag.clone(), value)))
+                .collect()
+        };
+        match address.as_deref().map(|address| address.parse::<IpAddr>()) {
+            Some(Ok(ip)) => Asset { address: Some(ip), hostname, tags },
+            // An address column holding a name is as good as a hostname column
+            Some(Err(_)) => Asset { address: None, hostname: hostname.or(address), tags },
+            None => Asset { address: None, hostname, tags },
+        }
+    }
+}
+
+/// Read an inventory file and list the hosts in it
+pub fn import_inventory(source: &InventorySource, map: &ColumnMap) -> Result<Vec<Asset>, String> {
+    let contents = fs::read_to_string(&source.path)
+        .map_err(|e| format!("Failed to read {}: {}", source.path, e))?;
+    parse_inventory(source.format, &contents, map).map_err(|e| format!("{}: {}", source.path, e))
+}
+
+/// List the hosts of an inventory already in memory; `map` only applies to CSV and JSON
+pub fn parse_inventory(format: InventoryFormat, contents: &str, map: &ColumnMap) -> Result<Vec<Asset>, String> {
+    match format {
+        InventoryFormat::Terraform => parse_terraform(&parse_json(contents)?),
+        InventoryFormat::Ansible if contents.trim_start().starts_with('{') => parse_ansible_json(&parse_json(contents)?),
+        InventoryFormat::Ansible => parse_ansible_ini(contents),
+        InventoryFormat::Aws => parse_aws(&parse_json(contents)?),
+        InventoryFormat::Csv => parse_csv(contents, map),
+        InventoryFormat::Json => parse_generic_json(&parse_json(contents)?, map),
+    }
+}
+
+fn parse_json(contents: &str) -> Result<Value, String> {
+    serde_json::from_str(contents).map_err(|e| format!("Invalid JSON: {}", e))
+}
+
+/// Resources of a state file (`resources[].instances[].attributes`) or of
+/// `terraform show -json` (`values.root_module`, recursing into child modules)
+fn parse_terraform(state: &Value) -> Result<Vec<Asset>, String> {
+    let mut assets = Vec::new();
+    if let Some(resources) = state["resources"].as_array() {
+        for resource in resources.iter().filter(|resource| resource["mode"] != "data") {
+            let mut address = format!("{}.{}", str_field(resource, "type"), str_field(resource, "name"));
+            if let Some(module) = resource["module"].as_str() {
+                address = format!("{}.{}", module, address);
+            }
+            for instance in resource["instances"].as_array().into_iter().flatten() {
+                let address = match &instance["index_key"] {
+                    Value::Null => address.clone(),
+                    key => format!("{}[{}]", address, key),
+                };
+                terraform_assets(&address, &instance["attributes"], &mut assets);
+            }
+        }
+    } else if state["values"]["root_module"].is_object() {
+        let mut modules = vec![&state["values"]["root_module"]];
+        while let Some(module) = modules.pop() {
+            for resource in module["resources"].as_array().into_iter().flatten() {
+                if resource["mode"] != "data" {
+                    terraform_assets(str_field(resource, "address"), &resource["values"], &mut assets);
+                }
+            }
+            modules.extend(module["child_modules"].as_array().into_iter().flatten());
+        }
+    } else {
+        return Err("Not a Terraform state: no \"resources\" or \"values.root_module\"".to_string());
+    }
+    Ok(assets)
+}
+
+fn terraform_assets(resource: &str, attributes: &Value, assets: &mut Vec<Asset>) {
+    let mut addresses = BTreeSet::new();
+    collect_addresses(attributes, &mut addresses);
+    let mut tags = BTreeMap::from([("resource".to_string(), resource.to_string())]);
+    if let Some(id) = attributes["id"].as_str() {
+        tags.insert("id".to_string(), id.to_string());
+    }
+    if let Some(name) = attributes["tags"]["Name"].as_str() {
+        tags.insert("name".to_string(), name.to_string());
+    }
+    for address in addresses {
+        assets.push(Asset { address: Some(address), hostname: None, tags: tags.clone() });
+    }
+}
+
+/// Addresses under any of [`TERRAFORM_ADDRESS_KEYS`], at any depth
+fn collect_addresses(value: &Value, addresses: &mut BTreeSet<IpAddr>) {
+    match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                if TERRAFORM_ADDRESS_KEYS.contains(&key.as_str()) {
+                    let values = match value {
+                        Value::Array(values) => values.iter().collect(),
+                        value => vec![value],
+                    };
+                    addresses.extend(values.iter().filter_map(|value| value.as_str()?.parse::<IpAddr>().ok()));
+                } else {
+                    collect_addresses(value, addresses);
+                }
+            }
+        }
+        Value::Array(values) => values.iter().for_each(|value| collect_addresses(value, addresses)),
+        _ => {}
+    }
+}
+
+/// Instances of `describe-instances` output, whether whole (`Reservations`) or
+/// narrowed by `--query` to a (possibly nested) array of instances
+fn parse_aws(output: &Value) -> Result<Vec<Asset>, String> {
+    let mut instances = Vec::new();
+    let mut pending = vec![output];
+    while let Some(value) = pending.pop() {
+        match value {
+            Value::Array(values) => pending.extend(values.iter().rev()),
+            Value::Object(fields) if fields.contains_key("InstanceId") => instances.push(value),
+            Value::Object(fields) => {
+                for key in ["Reservations", "Instances"] {
+                    if let Some(values) = fields.get(key) {
+                        pending.push(values);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    if instances.is_empty() && output.is_object() && output.get("Reservations").is_none() && output.get("Instances").is_none() {
+        return Err("Not describe-instances output: no \"Reservations\" or instances".to_string());
+    }
+
+    let mut assets = Vec::new();
+    for instance in instances {
+        let mut addresses = BTreeSet::new();
+        let mut add = |value: &Value| {
+            if let Some(ip) = value.as_str().and_then(|value| value.parse::<IpAddr>().ok()) {
+                addresses.insert(ip);
+            }
+        };
+        add(&instance["PrivateIpAddress"]);
+        add(&instance["PublicIpAddress"]);
+        add(&instance["Ipv6Address"]);
+        for interface in instance["NetworkInterfaces"].as_array().into_iter().flatten() {
+            for private in interface["PrivateIpAddresses"].as_array().into_iter().flatten() {
+                add(&private["PrivateIpAddress"]);
+                add(&private["Association"]["PublicIp"]);
+            }
+            for ipv6 in interface["Ipv6Addresses"].as_array().into_iter().flatten() {
+                add(&ipv6["Ipv6Address"]);
+            }
+        }
+
+        let mut tags = BTreeMap::from([("instance_id".to_string(), str_field(instance, "InstanceId").to_string())]);
+        if let Some(state) = instance["State"]["Name"].as_str() {
+            tags.insert("state".to_string(), state.to_string());
+        }
+        for tag in instance["Tags"].as_array().into_iter().flatten() {
+            if let (Some(key), Some(value)) = (tag["Key"].as_str(), tag["Value"].as_str()) {
+                let key = if key == "Name" { "name".to_string() } else { format!("tag:{}", key) };
+                tags.insert(key, value.to_string());
+            }
+        }
+        for address in addresses {
+            assets.push(Asset { address: Some(address), hostname: None, tags: tags.clone() });
+        }
+    }
+    Ok(assets)
+}
+
+/// Ansible host as listed in an inventory
+#[derive(Default)]
+struct AnsibleHost {
+    ansible_host: Option<String>,
+    groups: BTreeSet<String>,
+}
+
+/// INI inventory: `[group]` host lines with `key=value` variables, `[group:children]`
+/// nesting and `[group:vars]` sections, which are ignored. Hosts may use numeric
+/// ranges such as `web[01:20].example.com`.
+fn parse_ansible_ini(contents: &str) -> Result<Vec<Asset>, String> {
+    let first = contents.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with('#'));
+    if first.is_some_and(|line| line.ends_with(':') || line.starts_with("---")) {
+        return Err("YAML inventories are not supported; convert with `ansible-inventory -i FILE --list`".to_string());
+    }
+
+    let mut hosts: BTreeMap<String, AnsibleHost> = BTreeMap::new();
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    let mut group = "ungrouped".to_string();
+    let mut section = "hosts";
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            let (name, kind) = header.split_once(':').unwrap_or((header, "hosts"));
+            group = name.to_string();
+            section = match kind {
+                "hosts" => "hosts",
+                "children" => "children",
+                "vars" => "vars",
+                _ => return Err(format!("line {}: unknown section type [{}]", number + 1, header)),
+            };
+            continue;
+        }
+        let mut fields = line.split_whitespace();
+        let name = fields.next().unwrap_or_default();
+        match section {
+            "hosts" => {
+                let ansible_host = fields
+                    .filter_map(|field| field.split_once('='))
+                    .find(|(key, _)| *key == "ansible_host")
+                    .map(|(_, value)| value.trim_matches(|c| c == '"' || c == '\'').to_string());
+                for name in expand_host_range(name).map_err(|e| format!("line {}: {}", number + 1, e))? {
+                    let host = hosts.entry(name).or_default();
+                    if ansible_host.is_some() {
+                        host.ansible_host = ansible_host.clone();
+                    }
+                    host.groups.insert(group.clone());
+                }
+            }
+            "children" => children.entry(group.clone()).or_default().push(name.to_string()),
+            _ => {}
+        }
+    }
+
+    // A host belongs to every group its groups are nested in
+    let mut parents: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (parent, kids) in &children {
+        for kid in kids {
+            parents.entry(kid.as_str()).or_default().push(parent.as_str());
+        }
+    }
+    for host in hosts.values_mut() {
+        let mut pending: Vec<String> = host.groups.iter().cloned().collect();
+        while let Some(group) = pending.pop() {
+            for parent in parents.get(group.as_str()).into_iter().flatten() {
+                if host.groups.insert(parent.to_string()) {
+                    pending.push(parent.to_string());
+                }
+            }
+        }
+    }
+    Ok(ansible_assets(hosts))
+}
+
+/// Output of `ansible-inventory --list`: groups with `hosts` and `children`, and
+/// host variables under `_meta.hostvars`
+fn parse_ansible_json(inventory: &Value) -> Result<Vec<Asset>, String> {
+    let groups = inventory.as_object()
+        .ok_or_else(|| "Not an ansible-inventory --list document".to_string())?;
+    let mut hosts: BTreeMap<String, AnsibleHost> = BTreeMap::new();
+    // Walk down from every group so hosts inherit the groups above them
+    let mut pending: Vec<(&str, Vec<String>)> = groups.keys()
+        .filter(|name| *name != "_meta")
+        .map(|name| (name.as_str(), Vec::new()))
+        .collect();
+    while let Some((name, mut ancestors)) = pending.pop() {
+        if ancestors.iter().any(|ancestor| ancestor == name) {
+            continue;
+        }
+        ancestors.push(name.to_string());
+        for host in groups.get(name).and_then(|group| group["hosts"].as_array()).into_iter().flatten() {
+            if let Some(host) = host.as_str() {
+                hosts.entry(host.to_string()).or_default().groups.extend(ancestors.iter().cloned());
+            }
+        }
+        for child in groups.get(name).and_then(|group| group["children"].as_array()).into_iter().flatten() {
+            if let Some(child) = child.as_str() {
+                pending.push((child, ancestors.clone()));
+            }
+        }
+    }
+    for (name, vars) in inventory["_meta"]["hostvars"].as_object().into_iter().flatten() {
+        let host = hosts.entry(name.clone()).or_default();
+        host.ansible_host = vars["ansible_host"].as_str().map(str::to_string);
+    }
+    Ok(ansible_assets(hosts))
+}
+
+fn ansible_assets(hosts: BTreeMap<String, AnsibleHost>) -> Vec<Asset> {
+    hosts.into_iter()
+        .map(|(name, host)| {
+            let groups: Vec<String> = host.groups.into_iter()
+                .filter(|group| group != "all" && group != "ungrouped")
+                .collect();
+            let mut tags = BTreeMap::new();
+            if name.parse::<IpAddr>().is_err() {
+                tags.insert("inventory_host".to_string(), name.clone());
+            }
+            if !groups.is_empty() {
+                tags.insert("group".to_string(), groups.join(","));
+            }
+            let target = host.ansible_host.unwrap_or_else(|| name.clone());
+            match target.parse::<IpAddr>() {
+                Ok(ip) => Asset { address: Some(ip), hostname: name.parse::<IpAddr>().is_err().then_some(name), tags },
+                Err(_) => Asset { address: None, hostname: Some(target), tags },
+            }
+        })
+        .collect()
+}
+
+/// Expand an Ansible host pattern with one numeric range, keeping zero padding:
+/// `db[08:10]` is `db08`, `db09` and `db10`
+fn expand_host_range(pattern: &str) -> Result<Vec<String>, String> {
+    let Some((prefix, rest)) = pattern.split_once('[') else {
+        return Ok(vec![pattern.to_string()]);
+    };
+    let (range, suffix) = rest.split_once(']')
+        .ok_or_else(|| format!("unclosed range in host {}", pattern))?;
+    let (start, end) = range.split_once(':')
+        .ok_or_else(|| format!("expected [start:end] in host {}", pattern))?;
+    let (first, last) = match (start.parse::<u32>(), end.parse::<u32>()) {
+        (Ok(first), Ok(last)) if first <= last => (first, last),
+        _ => return Err(format!("unsupported range [{}] in host {}", range, pattern)),
+    };
+    if last - first >= MAX_HOST_RANGE {
+        return Err(format!("range [{}] in host {} expands to more than {} hosts", range, pattern, MAX_HOST_RANGE));
+    }
+    let width = if start.starts_with('0') { start.len() } else { 0 };
+    Ok((first..=last).map(|n| format!("{}{:0width$}{}", prefix, n, suffix, width = width)).collect())
+}
+
+fn parse_csv(contents: &str, map: &ColumnMap) -> Result<Vec<Asset>, String> {
+    let mut reader = csv::Reader::from_reader(contents.as_bytes());
+    let headers: Vec<String> = reader.headers()
+        .map_err(|e| format!("Invalid CSV header: {}", e))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let fields: Vec<&str> = headers.iter().map(String::as_str).collect();
+    let mut assets = Vec::new();
+    for (number, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| format!("line {}: {}", number + 2, e))?;
+        let get = |column: &str| {
+            let position = headers.iter().position(|header| header.eq_ignore_ascii_case(column))?;
+            record.get(position).map(str::trim).filter(|value| !value.is_empty()).map(str::to_string)
+        };
+        assets.push(map.asset(&fields, get));
+    }
+    Ok(assets)
+}
+
+/// An array of objects, or an object holding one such array
+fn parse_generic_json(document: &Value, map: &ColumnMap) -> Result<Vec<Asset>, String> {
+    let records = match document {
+        Value::Array(records) => records,
+        Value::Object(fields) => fields.values()
+            .find_map(Value::as_array)
+            .ok_or_else(|| "Expected an array of objects".to_string())?,
+        _ => return Err("Expected an array of objects".to_string()),
+    };
+    let mut assets = Vec::new();
+    for record in records.iter().filter(|record| record.is_object()) {
+        let fields: Vec<&str> = record.as_object().into_iter().flatten()
+            .filter(|(_, value)| !value.is_object() && !value.is_array())
+            .map(|(key, _)| key.as_str())
+            .collect();
+        let get = |path: &str| {
+            let value = path.split('.').try_fold(record, |value, key| value.get(key))?;
+            match value {
+                Value::String(value) if !value.trim().is_empty() => Some(value.trim().to_string()),
+                Value::Number(_) | Value::Bool(_) => Some(value.to_string()),
+                _ => None,
+            }
+        };
+        assets.push(map.asset(&fields, get));
+    }
+    Ok(assets)
+}
+
+fn str_field<'a>(value: &'a Value, key: &str) -> &'a str {
+    value[key].as_str().unwrap_or_default()
+}
@@ -0,0 +1,38 @@
+//! Ruscan port scanning library.
+//!
+//! The `ruscan` binary is a thin command line front-end over this crate. Embedders
+//! build a [`Scanner`], hand it targets and ports, and either collect every
+//! [`ScanResult`] at once with [`Scanner::scan`] or consume them as they arrive with
+//! [`Scanner::scan_stream`].
+//!
+//! ```no_run
+//! use ruscan::Scanner;
+//!
+//! let results = Scanner::new()
+//!     .target("127.0.0.1".parse().unwrap())
+//!     .ports([22, 80, 443])
+//!     .concurrency(100)
+//!     .scan();
+//!
+//! for result in results.iter().filter(|r| r.status.is_open()) {
+//!     println!("{}:{} is open", result.host, result.port);
+//! }
+//! ```
+
+pub mod checkpoint;
+pub mod congestion;
+pub mod inventory;
+pub mod network;
+pub mod order;
+pub mod plan;
+pub mod ports;
+pub mod profile;
+pub mod rate;
+pub mod report;
+pub mod scanner;
+pub mod scope;
+pub mod service;
+pub mod timing;
+
+pub use report::{PortState, ReportGenerator, ScanResult};
+pub use scanner::{ScanStream, Scanner, StopHandle};
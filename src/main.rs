@@ -1,24 +1,29 @@
 mod args;
-mod network;
-mod report;
-mod service;
 
 use args::*;
 use clap::Parser;
-use std::sync::{Arc, Mutex};
-use std::thread;
-use std::net::{SocketAddr, TcpStream};
-use std::time::{Duration, Instant};
-use std::str::FromStr;
+use std::time::Instant;
 use chrono::{Local, Datelike, Timelike};
 use dns_lookup::lookup_host;
-use report::{ReportGenerator, ScanResult};
-use network::{parse_cidr, parse_ip_range, is_valid_ip};
+use ruscan::{ReportGenerator, Scanner};
+use ruscan::checkpoint::{Checkpoint, CheckpointWriter};
+use ruscan::inventory::import_inventory;
+use ruscan::profile::COMMON_PORTS;
+use ruscan::scope::load_scope;
+use ruscan::network::{load_target_file, local_subnets, parse_cidr, parse_scoped, parse_target, parse_target_list, read_input_list, ClassAction, InputEntry, TargetSet, TargetSpec};
+use ruscan::ports::{parse_port_spec, top_ports, PortSpec, Protocol};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Write};
+use std::net::IpAddr;
 
 const RED : &str = "\x1b[31m";
 const GREEN : &str = "\x1b[32m";
 const BLUE : &str = "\x1b[34m";
 const RESET : &str = "\x1b[0m";
+
+/// Targets listed on the terminal by --dry-run; -f saves the full list
+const DRY_RUN_TARGET_LIMIT: usize = 256;
+
 const STARING: &str = r"
  ____  _   _ ____   ____    _    _   _ 
 |  _ \| | | / ___| / ___|  / \  | \ | |
@@ -28,111 +33,30 @@ const STARING: &str = r"
 
                                       ";
 
-fn init_port_scan(
-    addrs: Vec<String>,
-    ports: Vec<u16>,
-    service_detection: bool,
-    max_threads: usize,
-    reporter: Arc<Mutex<ReportGenerator>>,
-    only_open: bool,
-) {
-    let semaphore = Arc::new(Mutex::new(0));
-    let mut handles = vec![];
-
-    for addr in addrs {
-        for port in &ports {
-            // Rate control: wait if we have too many threads
-            loop {
-                let count = *semaphore.lock().unwrap();
-                if count < max_threads {
-                    *semaphore.lock().unwrap() = count + 1;
-                    break;
-                }
-                thread::sleep(Duration::from_millis(10));
-            }
-
-            let addr_clone = addr.clone();
-            let port_clone = *port;
-            let reporter_clone = reporter.clone();
-            let semaphore_clone = semaphore.clone();
-
-            let handle = thread::spawn(move || {
-                let host = format!("{}:{}", addr_clone, port_clone);
-                let socket_addr = match SocketAddr::from_str(&host) {
-                    Ok(addr) => addr,
-                    Err(_) => {
-                        *semaphore_clone.lock().unwrap() -= 1;
-                        return;
-                    }
-                };
-
-                let (status, service, version) = match TcpStream::connect_timeout(&socket_addr, Duration::from_secs(3)) {
-                    Ok(_) => {
-                        if service_detection {
-                            let (svc, ver) = service::detect_service(&socket_addr);
-                            (String::from("open"), svc, ver)
-                        } else {
-                            (String::from("open"), None, None)
-                        }
-                    }
-                    Err(_) => (String::from("closed"), None, None),
-                };
-
-                // Print result (only open ports if scanning multiple IPs)
-                if status == "open" {
-                    let service_info = if let Some(ref svc) = service {
-                        if let Some(ref ver) = version {
-                            format!(" ({}/{})", svc, ver)
-                        } else {
-                            format!(" ({})", svc)
-                        }
-                    } else {
-                        String::new()
-                    };
-                    println!("{}[+] {}:{} is open{}{}", GREEN, addr_clone, port_clone, service_info, RESET);
-                } else if !only_open {
-                    // Only print closed ports if not scanning multiple IPs
-                    // println!("{}[-] {}:{} is closed{}", RED, addr_clone, port_clone, RESET);
-                }
-
-                // Add to report
-                let result = ScanResult {
-                    host: addr_clone,
-                    port: port_clone,
-                    status: status.clone(),
-                    service: service.clone(),
-                    version: version.clone(),
-                };
-                reporter_clone.lock().unwrap().add_result(result);
-
-                *semaphore_clone.lock().unwrap() -= 1;
-            });
-
-            handles.push(handle);
-        }
-    }
-
-    for handle in handles {
-        handle.join().unwrap();
-    }
-}
-
 fn main(){
     println!("\t{}{}{}",BLUE, STARING, RESET);
     let time = Local::now();
     let (hour, minute, second) = (time.hour(), time.minute(), time.second());
     let (year, month, day) = (time.year(), time.month(), time.day());
     println!("{}Starting Ruscan ( https://github.com/sharkvdwho/ruscan ) at {}-{:02}-{:02} {:02}:{:02}:{:02}{} \n",GREEN, year, month, day, hour, minute, second, RESET);
-    let args = RuscanArgs::parse();
+    let args = RuscanArgs::parse_from(normalized_args());
 
     match args.entity_type {
         EntityType::Ps(port_scan) => {
-            let mut addrs: Vec<String> = Vec::new();
+            let mut addrs = TargetSet::new();
 
             // Handle single IP
             if let Some(ip) = port_scan.ip {
-                if is_valid_ip(&ip) {
-                    addrs.push(ip);
+                if ip.contains('%') {
+                    match parse_scoped(&ip) {
+                        Ok(spec) => addrs.add(spec),
+                        Err(e) => {
+                            println!("{}[-] {}{}", RED, e, RESET);
+                            return;
+                        }
+                    }
+                } else if let Ok(ip) = ip.parse::<IpAddr>() {
+                    addrs.add_ip(ip);
                 } else {
                     println!("{}[-] Invalid IP address: {}{}", RED, ip, RESET);
                     return;
@@ -145,7 +69,7 @@ fn main(){
                 match resolved {
                     Ok(res) => {
                         for ip in res {
-                            addrs.push(ip.to_string());
+                            addrs.add_host(&domain, ip);
                         }
                     },
                     Err(_) => {
@@ -158,9 +82,9 @@ fn main(){
             // Handle CIDR notation
             if let Some(cidr) = port_scan.cidr {
                 match parse_cidr(&cidr) {
-                    Ok(ips) => {
-                        println!("{}[+] Scanning {} IPs from CIDR: {}{}", GREEN, ips.len(), cidr, RESET);
-                        addrs.extend(ips);
+                    Ok(spec) => {
+                        println!("{}[+] Scanning {} IPs from CIDR: {}{}", GREEN, spec.len(), cidr, RESET);
+                        addrs.add(spec);
                     },
                     Err(e) => {
                         println!("{}[-] Error parsing CIDR: {}{}", RED, e, RESET);
@@ -171,10 +95,10 @@ fn main(){
 
             // Handle IP range
             if let Some(ip_range) = port_scan.ip_range {
-                match parse_ip_range(&ip_range) {
-                    Ok(ips) => {
-                        println!("{}[+] Scanning {} IPs from range: {}{}", GREEN, ips.len(), ip_range, RESET);
-                        addrs.extend(ips);
+                match parse_target(&ip_range) {
+                    Ok(spec) => {
+                        println!("{}[+] Scanning {} IPs from range: {}{}", GREEN, spec.len(), ip_range, RESET);
+                        addrs.add(spec);
                     },
                     Err(e) => {
                         println!("{}[-] Error parsing IP range: {}{}", RED, e, RESET);
@@ -183,89 +107,502 @@ fn main(){
                 }
             }
 
-            if addrs.is_empty() {
-                println!("{}[-] No target address specified. Use -i, -d, -c, or -R option.{}", RED, RESET);
-                return;
+            // Handle positional targets in nmap syntax
+            if !port_scan.targets.is_empty() {
+                match parse_target_list(&port_scan.targets.join(" ")) {
+                    Ok(targets) => {
+                        println!("{}[+] Scanning {} IPs from {} target(s){}", GREEN, targets.len(), targets.specs().len(), RESET);
+                        addrs.extend(targets);
+                    },
+                    Err(e) => {
+                        println!("{}[-] Error parsing targets: {}{}", RED, e, RESET);
+                        return;
+                    }
+                }
+            }
+
+            // Handle target list file or stdin
+            let mut explicit_pairs: Vec<(IpAddr, u16)> = Vec::new();
+            if let Some(path) = &port_scan.input_list {
+                let entries = if path == "-" {
+                    read_input_list(io::stdin().lock(), "stdin")
+                } else {
+                    File::open(path)
+                        .map_err(|e| format!("Failed to open {}: {}", path, e))
+                        .and_then(|file| read_input_list(BufReader::new(file), path))
+                };
+                let entries = match entries {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        println!("{}[-] Error reading target list: {}{}", RED, e, RESET);
+                        return;
+                    }
+                };
+                let (before, pairs_before) = (addrs.len(), explicit_pairs.len());
+                for entry in entries {
+                    match entry {
+                        InputEntry::Spec(spec) => addrs.add(spec),
+                        InputEntry::Hostname(host) => match lookup_host(&host) {
+                            Ok(ips) => ips.into_iter().for_each(|ip| addrs.add_host(&host, ip)),
+                            Err(_) => println!("{}[-] Could not resolve {}, skipping it{}", RED, host, RESET),
+                        },
+                        InputEntry::Pair(host, port) if host.contains('%') => {
+                            if let Ok(TargetSpec::Scoped(ip, zone)) = parse_scoped(&host) {
+                                addrs.set_zone(ip, zone);
+                                explicit_pairs.push((IpAddr::V6(ip), port));
+                            }
+                        }
+                        InputEntry::Pair(host, port) => match host.parse::<IpAddr>() {
+                            Ok(ip) => explicit_pairs.push((ip, port)),
+                            Err(_) => match lookup_host(&host) {
+                                Ok(ips) => {
+                                    for ip in ips {
+                                        addrs.record_hostname(ip, &host);
+                                        explicit_pairs.push((ip, port));
+                                    }
+                                },
+                                Err(_) => println!("{}[-] Could not resolve {}, skipping it{}", RED, host, RESET),
+                            },
+                        },
+                    }
+                }
+                println!("{}[+] Read {} IPs and {} host:port pairs from {}{}",
+                    GREEN, addrs.len() - before, explicit_pairs.len() - pairs_before,
+                    if path == "-" { "stdin" } else { path }, RESET);
+            }
+
+            // Handle infrastructure inventories
+            let column_map = port_scan.inventory_map.clone().unwrap_or_default();
+            for source in &port_scan.inventory {
+                let assets = match import_inventory(source, &column_map) {
+                    Ok(assets) => assets,
+                    Err(e) => {
+                        println!("{}[-] Error importing inventory: {}{}", RED, e, RESET);
+                        return;
+                    }
+                };
+                let before = addrs.len();
+                for asset in &assets {
+                    let ips = match (asset.address, &asset.hostname) {
+                        (Some(ip), _) => vec![ip],
+                        (None, Some(host)) => match lookup_host(host) {
+                            Ok(ips) => ips,
+                            Err(_) => {
+                                println!("{}[-] Could not resolve {}, skipping it{}", RED, host, RESET);
+                                continue;
+                            }
+                        },
+                        (None, None) => continue,
+                    };
+                    for ip in ips {
+                        match &asset.hostname {
+                            Some(host) => addrs.add_host(host, ip),
+                            None => addrs.add_ip(ip),
+                        }
+                        for (key, value) in &asset.tags {
+                            addrs.add_tag(ip, key, value);
+                        }
+                    }
+                }
+                println!("{}[+] Imported {} IPs from {} asset(s) in {} ({} inventory){}",
+                    GREEN, addrs.len() - before, assets.len(), source.path, source.format.name(), RESET);
             }
 
-            // Determine ports to scan
-            let mut ports: Vec<u16> = Vec::new();
+            // Handle subnets of the local network interfaces
+            if port_scan.local_subnets || port_scan.interface.is_some() {
+                match local_subnets(port_scan.interface.as_deref()) {
+                    Ok(subnets) if subnets.is_empty() => {
+                        println!("{}[!] No subnets found on {}{}", RED,
+                            port_scan.interface.as_deref().unwrap_or("any non-loopback interface"), RESET);
+                    }
+                    Ok(subnets) => {
+                        for subnet in subnets {
+                            let spec = subnet.to_target();
+                            if subnet.is_narrowed() {
+                                println!("{}[!] {}/{} on {} is too large to scan; narrowed to {} around {}{}",
+                                    RED, subnet.address, subnet.prefix_len, subnet.interface, subnet.network, subnet.address, RESET);
+                            }
+                            println!("{}[+] Scanning {} IPs from {} on {}{}", GREEN, spec.len(), subnet.network, subnet.interface, RESET);
+                            addrs.add(spec);
+                        }
+                    }
+                    Err(e) => {
+                        println!("{}[-] {}{}", RED, e, RESET);
+                        return;
+                    }
+                }
+            }
 
-            if let Some(port) = port_scan.port {
-                ports.push(port);
+            if addrs.duplicates() > 0 {
+                println!("{}[+] Ignoring {} duplicate address(es) given by more than one target{}", GREEN, addrs.duplicates(), RESET);
             }
 
-            if let Some(range) = port_scan.range {
-                let mut split = range.split("-");
-                let start: u16 = split.next()
-                    .expect(&format!("{}[-] Invalid range value, see ruscan ps --help for more information{}", RED, RESET))
-                    .parse()
-                    .unwrap();
-                let end: u16 = split.next()
-                    .expect(&format!("{}[-] Invalid range value, see ruscan ps --help for more information{}", RED, RESET))
-                    .parse()
-                    .unwrap();
-                ports.extend(start..=end);
+            if addrs.is_empty() && explicit_pairs.is_empty() {
+                println!("{}[-] No target address specified. Give targets as arguments or use -i, -d, -c, -R, -iL, --inventory or --local-subnets option.{}", RED, RESET);
+                return;
             }
 
-            if let Some(list) = port_scan.list {
-                let mut split = list.split(",");
-                while let Some(v) = split.next() {
-                    if let Ok(port) = v.trim().parse::<u16>() {
-                        ports.push(port);
+            // Addresses and ports that must never be connected to
+            let mut exclude = TargetSet::new();
+            if let Some(list) = &port_scan.exclude {
+                match parse_target_list(list) {
+                    Ok(targets) => exclude.extend(targets),
+                    Err(e) => {
+                        println!("{}[-] Error parsing --exclude: {}{}", RED, e, RESET);
+                        return;
+                    }
+                }
+            }
+            if let Some(path) = &port_scan.exclude_file {
+                match load_target_file(path) {
+                    Ok(targets) => exclude.extend(targets),
+                    Err(e) => {
+                        println!("{}[-] Error reading --exclude-file: {}{}", RED, e, RESET);
+                        return;
                     }
                 }
             }
+            let exclude_ports: Vec<u16> = match port_scan.exclude_ports.as_deref().map(parse_port_spec) {
+                Some(Ok(spec)) => spec.tcp().collect(),
+                Some(Err(e)) => {
+                    println!("{}[-] Error parsing --exclude-ports: {}{}", RED, e, RESET);
+                    return;
+                }
+                None => Vec::new(),
+            };
+            if !exclude.is_empty() || !exclude_ports.is_empty() {
+                println!("{}[+] Excluding {} address(es) and {} port(s); excluded pairs are reported as skipped{}",
+                    GREEN, exclude.len(), exclude_ports.len(), RESET);
+            }
 
-            // Default to common ports if none specified
-            if ports.is_empty() {
-                ports = vec![21, 22, 23, 25, 53, 80, 110, 143, 443, 993, 995, 3306, 3389, 5432, 8080];
-                println!("{}[+] No ports specified, scanning common ports{}", GREEN, RESET);
+            // Scope every target is checked against before connecting
+            let scope = match port_scan.scope.as_deref().map(load_scope) {
+                Some(Ok(scope)) => Some(scope),
+                Some(Err(e)) => {
+                    println!("{}[-] {}{}", RED, e, RESET);
+                    return;
+                }
+                None => None,
+            };
+
+            // Determine ports to scan; -p, -r and -l share one grammar and overlapping
+            // specs are merged so no port is scanned twice
+            let mut port_spec = PortSpec::default();
+            for (flag, spec) in [("-p", &port_scan.port), ("-r", &port_scan.range), ("-l", &port_scan.list)] {
+                if let Some(spec) = spec {
+                    match parse_port_spec(spec) {
+                        Ok(parsed) => port_spec.merge(parsed),
+                        Err(e) => {
+                            println!("{}[-] Error parsing {} {}: {}{}", RED, flag, spec, e, RESET);
+                            return;
+                        }
+                    }
+                }
+            }
+            let udp_ports = port_spec.udp().count();
+            if udp_ports > 0 {
+                println!("{}[!] UDP scanning is not supported yet, ignoring {} UDP port(s){}", RED, udp_ports, RESET);
+            }
+            let mut ports: Vec<u16> = port_spec.tcp().collect();
+            if let Some(n) = port_scan.top_ports {
+                let top = top_ports(n, Protocol::Tcp);
+                if top.len() < n {
+                    println!("{}[!] The service table ranks only {} TCP ports, scanning those (add -p for more){}", RED, top.len(), RESET);
+                }
+                println!("{}[+] Adding the top {} TCP ports from the service table{}", GREEN, top.len(), RESET);
+                // `ports` is still sorted here, so overlaps with -p/-r/-l are cheap to drop
+                let new: Vec<u16> = top.into_iter().filter(|port| ports.binary_search(port).is_err()).collect();
+                ports.extend(new);
+            }
+            if ports.is_empty() && !port_spec.is_empty() {
+                println!("{}[-] No TCP ports to scan{}", RED, RESET);
+                return;
+            }
+
+            // Fall back to the profile's ports, or common ports if none specified
+            if ports.is_empty() && !addrs.is_empty() {
+                if let Some(profile) = port_scan.profile {
+                    ports = profile.ports();
+                    println!("{}[+] Scanning ports from the {} profile{}", GREEN, profile.name(), RESET);
+                } else {
+                    ports = COMMON_PORTS.to_vec();
+                    println!("{}[+] No ports specified, scanning common ports{}", GREEN, RESET);
+                }
             }
 
+            // An explicit -T wins over the profile's template
+            let timing = port_scan.timing
+                .or(port_scan.profile.map(|p| p.timing()))
+                .unwrap_or_default();
+            let service_detection = port_scan.service_detection
+                || port_scan.profile.is_some_and(|p| p.service_detection());
+
             // Initialize reporter
-            let reporter = Arc::new(Mutex::new(ReportGenerator::new()));
+            let mut reporter = ReportGenerator::new();
 
             // Determine if we should only show open ports
             // Show only open ports if: scanning multiple IPs OR scanning a large port range (>100 ports)
-            let total_scans = addrs.len() * ports.len();
-            let only_open = addrs.len() > 1 || ports.len() > 100;
+            let host_count = addrs.len();
+            let only_open = host_count > 1 || ports.len() > 100 || explicit_pairs.len() > 1;
+
+            let mut scanner = Scanner::new()
+                .targets(addrs)
+                .ports(ports.iter().copied())
+                .service_detection(service_detection)
+                .timing(timing)
+                .adaptive_concurrency(!port_scan.fixed_concurrency)
+                .randomize(!port_scan.no_randomize)
+                .pairs(explicit_pairs.iter().copied())
+                .exclude(exclude)
+                .exclude_ports(exclude_ports)
+                .verbose(port_scan.verbose);
+            if let Some(threads) = port_scan.threads {
+                scanner = scanner.concurrency(threads);
+            }
+            if let Some(connect_timeout) = port_scan.connect_timeout {
+                scanner = scanner.connect_timeout(connect_timeout);
+            }
+            if let Some(read_timeout) = port_scan.read_timeout {
+                scanner = scanner.read_timeout(read_timeout);
+            }
+            if port_scan.adaptive_timeout {
+                scanner = scanner.adaptive_timeout(true);
+            }
+            if let Some(max_retries) = port_scan.max_retries {
+                scanner = scanner.max_retries(max_retries);
+            }
+            if let Some(max_per_host) = port_scan.max_per_host {
+                scanner = scanner.max_per_host(max_per_host);
+            }
+            if let Some(scan_delay) = port_scan.scan_delay {
+                scanner = scanner.scan_delay(scan_delay);
+            }
+            if let Some(max_scan_delay) = port_scan.max_scan_delay {
+                scanner = scanner.max_scan_delay(max_scan_delay);
+            }
+            if let Some(max_rate) = port_scan.max_rate {
+                scanner = scanner.max_rate(max_rate);
+            }
+            if let Some(min_rate) = port_scan.min_rate {
+                scanner = scanner.min_rate(min_rate);
+            }
+            if let Some(seed) = port_scan.seed {
+                scanner = scanner.seed(seed);
+            }
+            if let Some(scope) = scope.clone() {
+                scanner = scanner.scope(scope);
+            }
+            if let Some(policy) = port_scan.special_addresses {
+                scanner = scanner.address_policy(policy);
+            }
+
+            let plan = scanner.plan();
+            if let Some(path) = &port_scan.scope {
+                if plan.out_of_scope > 0 {
+                    println!("{}[!] {} target address(es) are outside the scope in {} and will be skipped{}", RED, plan.out_of_scope, path, RESET);
+                } else {
+                    println!("{}[+] Every target is inside the scope in {}{}", GREEN, path, RESET);
+                }
+                if plan.probes() == 0 {
+                    println!("{}[-] Nothing left to scan inside the scope{}", RED, RESET);
+                    return;
+                }
+            }
+            for (class, count) in &plan.special_addresses {
+                match plan.address_policy.action(*class) {
+                    ClassAction::Skip => println!("{}[!] Skipping {} {} address(es) (--special-addresses {}=allow to scan them){}", RED, count, class, class.name().split('/').next().unwrap_or_default(), RESET),
+                    ClassAction::Warn => println!("{}[!] {} target address(es) are {} addresses{}", RED, count, class, RESET),
+                    ClassAction::Allow => {}
+                }
+            }
+            if plan.probes() == 0 && plan.special_count(ClassAction::Skip) > 0 {
+                println!("{}[-] Nothing left to scan once special addresses are skipped{}", RED, RESET);
+                return;
+            }
+
+            // Show what would be scanned and stop before any connection is made
+            if port_scan.dry_run {
+                println!("{}[+] Dry run: {} probes planned, no connections will be made{}", GREEN, plan.probes(), RESET);
+                let written = match (port_scan.output.to_lowercase().as_str(), port_scan.file.as_deref()) {
+                    ("json", Some(path)) => File::create(path)
+                        .map_err(|e| format!("Failed to create file {}: {}", path, e))
+                        .and_then(|file| plan.write_json(BufWriter::new(file))),
+                    ("json", None) => plan.write_json(io::stdout().lock()).map(|_| println!()),
+                    (_, Some(path)) => File::create(path)
+                        .map_err(|e| format!("Failed to create file {}: {}", path, e))
+                        .and_then(|file| plan.write_text(BufWriter::new(file), None)),
+                    (_, None) => {
+                        print!("{}", plan.to_text(Some(DRY_RUN_TARGET_LIMIT)));
+                        Ok(())
+                    }
+                };
+                match (written, port_scan.file.as_deref()) {
+                    (Err(e), _) => println!("{}[-] {}{}", RED, e, RESET),
+                    (Ok(()), Some(path)) => println!("{}[+] Scan plan saved to {}{}", GREEN, path, RESET),
+                    (Ok(()), None) => {}
+                }
+                return;
+            }
+
+            if scope.as_ref().is_some_and(|scope| !scope.window_open(&Local::now())) {
+                println!("{}[-] Outside the time windows allowed by the scope in {}{}", RED, port_scan.scope.as_deref().unwrap_or_default(), RESET);
+                return;
+            }
+
+            // Large scans and public address space need an explicit go-ahead
+            let large = plan.probes() > port_scan.confirm_above;
+            if (large || plan.public_hosts > 0) && !port_scan.yes {
+                if large {
+                    println!("{}[!] This scan sends {} probes (more than --confirm-above {}){}", RED, plan.probes(), port_scan.confirm_above, RESET);
+                }
+                if plan.public_hosts > 0 {
+                    println!("{}[!] {} target address(es) are on the public internet{}", RED, plan.public_hosts, RESET);
+                }
+                if !io::stdin().is_terminal() {
+                    println!("{}[-] Refusing to start without confirmation; pass --yes to run unattended{}", RED, RESET);
+                    return;
+                }
+                print!("{}[?] Continue? [y/N] {}", BLUE, RESET);
+                let _ = io::stdout().flush();
+                let mut answer = String::new();
+                let confirmed = io::stdin().read_line(&mut answer).is_ok()
+                    && matches!(answer.trim().to_lowercase().as_str(), "y" | "yes");
+                if !confirmed {
+                    println!("{}[-] Scan cancelled{}", RED, RESET);
+                    return;
+                }
+            }
+
+            // Resume from the state file if it exists, otherwise start journaling to it
+            if let Some(state_file) = &port_scan.resume {
+                match Checkpoint::load(state_file) {
+                    Ok(Some(mut checkpoint)) => {
+                        // Replay the earlier run's order unless a seed was given explicitly
+                        if let (None, Some(seed)) = (port_scan.seed, checkpoint.seed()) {
+                            scanner = scanner.seed(seed);
+                        }
+                        if checkpoint.fingerprint() != scanner.fingerprint() {
+                            println!("{}[-] Refusing to resume: {} was written for a different target, port or ordering specification{}", RED, state_file, RESET);
+                            return;
+                        }
+                        println!("{}[+] Resuming from {}: {} of {} host/port pairs already done{}",
+                            GREEN, state_file, checkpoint.completed(), scanner.total_probes(), RESET);
+                        for result in checkpoint.take_results() {
+                            reporter.add_result(result);
+                        }
+                        match CheckpointWriter::append(state_file) {
+                            Ok(writer) => scanner = scanner.resume(checkpoint).checkpoint(writer),
+                            Err(e) => {
+                                println!("{}[-] {}{}", RED, e, RESET);
+                                return;
+                            }
+                        }
+                    },
+                    Ok(None) => match CheckpointWriter::create(state_file, &scanner.fingerprint(), scanner.order_seed()) {
+                        Ok(writer) => scanner = scanner.checkpoint(writer),
+                        Err(e) => {
+                            println!("{}[-] {}{}", RED, e, RESET);
+                            return;
+                        }
+                    },
+                    Err(e) => {
+                        println!("{}[-] {}{}", RED, e, RESET);
+                        return;
+                    }
+                }
+            }
 
             // Perform scan
-            println!("{}[+] Starting scan of {} host(s) on {} port(s) ({} total connections){}", 
-                GREEN, addrs.len(), ports.len(), total_scans, RESET);
+            // Pairs inside the host x port grid were deduplicated by the scanner
+            let extra_pairs = scanner.extra_pair_count();
+            if extra_pairs == 0 {
+                println!("{}[+] Starting scan of {} host(s) on {} port(s) ({} total connections){}",
+                    GREEN, host_count, ports.len(), scanner.total_probes(), RESET);
+            } else {
+                println!("{}[+] Starting scan of {} host(s) on {} port(s) plus {} host:port pair(s) ({} total connections){}",
+                    GREEN, host_count, ports.len(), extra_pairs, scanner.total_probes(), RESET);
+            }
             if only_open {
-                if addrs.len() > 1 {
+                if host_count > 1 {
                     println!("{}[+] Showing only open ports (scanning multiple IPs){}", BLUE, RESET);
                 } else {
                     println!("{}[+] Showing only open ports (scanning large port range){}", BLUE, RESET);
                 }
             }
+            println!("{}[+] Timing template: {}{}", BLUE, timing.name(), RESET);
+            match scanner.order_seed() {
+                Some(seed) => println!("{}[+] Randomized scan order (seed {}){}", BLUE, seed, RESET),
+                None if !port_scan.no_randomize => println!("{}[!] Scan space too large to randomize, scanning in order{}", RED, RESET),
+                None => {}
+            }
             println!("{}[+] Scanning...{}", BLUE, RESET);
             
             // Start timing the scan
             let start_time = Instant::now();
             
-            init_port_scan(
-                addrs.clone(),
-                ports,
-                port_scan.service_detection,
-                port_scan.threads,
-                reporter.clone(),
-                only_open,
-            );
+            let mut stream = scanner.scan_stream();
+
+            // First Ctrl-C / SIGTERM drains in-flight probes and keeps the partial
+            // results; a second one exits immediately
+            let stop = stream.stop_handle();
+            if let Err(e) = ctrlc::set_handler(move || {
+                if stop.is_stopped() {
+                    std::process::exit(130);
+                }
+                stop.stop();
+                println!("{}[!] Interrupted, waiting for in-flight probes (press Ctrl-C again to quit now){}", RED, RESET);
+            }) {
+                println!("{}[-] Could not install interrupt handler: {}{}", RED, e, RESET);
+            }
+
+            for result in stream.by_ref() {
+                // Print result (only open ports if scanning multiple IPs)
+                if result.status.is_open() {
+                    let service_info = if let Some(ref svc) = result.service {
+                        if let Some(ref ver) = result.version {
+                            format!(" ({}/{})", svc, ver)
+                        } else {
+                            format!(" ({})", svc)
+                        }
+                    } else {
+                        String::new()
+                    };
+                    let registered = result.registered_service.as_deref()
+                        .map(|name| format!(" [{}]", name))
+                        .unwrap_or_default();
+                    println!("{}[+] {}:{} is open{}{}{}", GREEN, result.host, result.port, registered, service_info, RESET);
+                } else if !only_open {
+                    // Only print non-open ports if not scanning multiple IPs
+                    // println!("{}[-] {}:{} is {}{}", RED, result.host, result.port, result.status, RESET);
+                }
+                reporter.add_result(result);
+            }
 
             // Calculate elapsed time in seconds with millisecond precision (3 decimal places)
             let elapsed = start_time.elapsed().as_secs_f64();
-            println!("{}[+] Scan completed in {:.3} seconds{}", GREEN, elapsed, RESET);
+            let probes = stream.probes_sent();
+            let achieved_rate = if elapsed > 0.0 { probes as f64 / elapsed } else { 0.0 };
+            if stream.was_stopped() {
+                println!("{}[!] Scan interrupted after {:.3} seconds ({} probes, {:.1} probes/s), {} host/port pairs not attempted{}",
+                    RED, elapsed, probes, achieved_rate, reporter.not_attempted(), RESET);
+                if scope.as_ref().is_some_and(|scope| !scope.window_open(&Local::now())) {
+                    println!("{}[!] The scope's time window closed; run the same command with --resume inside the next window to continue{}", RED, RESET);
+                }
+            } else {
+                println!("{}[+] Scan completed in {:.3} seconds ({} probes, {:.1} probes/s){}", GREEN, elapsed, probes, achieved_rate, RESET);
+            }
+            println!("{}[+] Port states: {}{}", GREEN, reporter.summary(), RESET);
 
             // Generate report (only if saving to file or using non-text format)
-            let reporter_guard = reporter.lock().unwrap();
             if port_scan.file.is_some() || port_scan.output.to_lowercase() != "text" {
-                if let Err(e) = reporter_guard.generate(&port_scan.output, port_scan.file.as_deref()) {
+                if let Err(e) = reporter.generate(&port_scan.output, port_scan.file.as_deref()) {
                     println!("{}[-] Error generating report: {}{}", RED, e, RESET);
                 }
             }
+
+            if stream.was_stopped() {
+                std::process::exit(130);
+            }
         },
     }
 }
@@ -1,29 +1,975 @@
 use ipnet::IpNet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+use std::sync::OnceLock;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
-/// Parse CIDR notation and return a list of IP addresses
-pub fn parse_cidr(cidr_str: &str) -> Result<Vec<String>, String> {
-    let network: IpNet = cidr_str.parse()
-        .map_err(|_| format!("Invalid CIDR notation: {}", cidr_str))?;
-    
-    let mut ips = Vec::new();
-    match network {
-        IpNet::V4(net) => {
-            for ip in net.hosts() {
-                ips.push(ip.to_string());
+/// Most separate address ranges a single nmap-style pattern may expand to
+pub const MAX_PATTERN_FRAGMENTS: u128 = 1 << 20;
+
+/// Shortest prefix of a local IPv4 subnet scanned whole; broader subnets are
+/// narrowed to this size around the interface address
+pub const LOCAL_SUBNET_MIN_PREFIX_V4: u8 = 16;
+
+/// Shortest prefix of a local IPv6 subnet scanned whole, see [`LOCAL_SUBNET_MIN_PREFIX_V4`]
+pub const LOCAL_SUBNET_MIN_PREFIX_V6: u8 = 112;
+
+/// Blocks that are not reachable on the public internet: unspecified, private,
+/// shared, loopback, link-local, documentation, benchmarking, multicast and reserved
+pub const NON_PUBLIC_RANGES: [&str; 22] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.0.2.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "198.51.100.0/24",
+    "203.0.113.0/24",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/127",
+    "100::/64",
+    "2001:2::/48",
+    "2001:db8::/32",
+    "3fff::/20",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+/// One block of target addresses, expanded lazily
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetSpec {
+    Single(IpAddr),
+    /// The usable hosts of a network (IPv4 network and broadcast addresses excluded)
+    Cidr(IpNet),
+    /// Inclusive `start-end` range of the same IP version
+    Range(IpAddr, IpAddr),
+    /// nmap-style pattern such as `192.168.1-3.1-254` or `10.0.0,2,4.*`
+    Pattern(AddressPattern),
+    /// IPv6 address bound to an interface, such as the link-local `fe80::1%eth0`
+    Scoped(Ipv6Addr, Zone),
+}
+
+/// Interface an IPv6 address is scoped to: its index, and the name it was given by
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Zone {
+    pub id: u32,
+    pub name: String,
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// Sets of values for each octet (IPv4) or 16-bit group (IPv6) of an address.
+///
+/// Each field holds sorted, non-overlapping inclusive ranges; the addresses are every
+/// combination of one value per field, enumerated with the last field varying fastest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressPattern {
+    v6: bool,
+    fields: Vec<Vec<(u16, u16)>>,
+}
+
+impl AddressPattern {
+    pub fn len(&self) -> u128 {
+        self.fields.iter().fold(1u128, |total, field| total.saturating_mul(field_len(field)))
+    }
+
+    /// A pattern always matches at least one address
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn get(&self, mut index: u128) -> Option<IpAddr> {
+        if index >= self.len() {
+            return None;
+        }
+        let mut values = vec![0u16; self.fields.len()];
+        for (value, field) in values.iter_mut().zip(&self.fields).rev() {
+            let len = field_len(field);
+            let mut digit = index % len;
+            index /= len;
+            for &(start, end) in field {
+                let span = (end - start) as u128 + 1;
+                if digit < span {
+                    *value = start + digit as u16;
+                    break;
+                }
+                digit -= span;
+            }
+        }
+        Some(if self.v6 {
+            let mut groups = [0u16; 8];
+            groups.copy_from_slice(&values);
+            IpAddr::V6(Ipv6Addr::from(groups))
+        } else {
+            IpAddr::V4(Ipv4Addr::new(values[0] as u8, values[1] as u8, values[2] as u8, values[3] as u8))
+        })
+    }
+
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        let values: Vec<u16> = match ip {
+            IpAddr::V4(ip) if !self.v6 => ip.octets().iter().map(|&octet| octet as u16).collect(),
+            IpAddr::V6(ip) if self.v6 => ip.segments().to_vec(),
+            _ => return false,
+        };
+        values.iter().zip(&self.fields)
+            .all(|(value, field)| field.iter().any(|&(start, end)| start <= *value && *value <= end))
+    }
+
+    /// Number of the last field whose values are not the whole range, after which
+    /// every field is a wildcard; `None` when the pattern matches every address
+    fn last_partial_field(&self) -> Option<usize> {
+        let max = if self.v6 { 0xffff } else { 0xff };
+        self.fields.iter().rposition(|field| field.as_slice() != [(0, max)])
+    }
+
+    /// How many separate intervals the pattern's addresses form
+    fn fragments(&self) -> u128 {
+        match self.last_partial_field() {
+            Some(partial) => self.fields[..partial].iter()
+                .fold(self.fields[partial].len() as u128, |total, field| total.saturating_mul(field_len(field))),
+            None => 1,
+        }
+    }
+
+    /// The addresses as sorted inclusive integer intervals
+    fn intervals(&self) -> Vec<(u128, u128)> {
+        let bits = if self.v6 { 16 } else { 8 };
+        let Some(partial) = self.last_partial_field() else {
+            let max = if self.v6 { u128::MAX } else { u32::MAX as u128 };
+            return vec![(0, max)];
+        };
+        // Fields after `partial` are wildcards and widen every interval
+        let shift = bits * (self.fields.len() - 1 - partial);
+        let width_mask = (1u128 << shift) - 1;
+        let mut bases = vec![0u128];
+        for field in &self.fields[..partial] {
+            bases = bases.iter()
+                .flat_map(|base| field.iter().flat_map(move |&(start, end)| {
+                    (start..=end).map(move |value| (base << bits) | value as u128)
+                }))
+                .collect();
+        }
+        bases.iter()
+            .flat_map(|base| self.fields[partial].iter().map(move |&(start, end)| {
+                let prefix = ((base << bits) | start as u128) << shift;
+                let last = (((base << bits) | end as u128) << shift) | width_mask;
+                (prefix, last)
+            }))
+            .collect()
+    }
+
+    /// The single address matched when every field holds exactly one value
+    fn single(&self) -> Option<IpAddr> {
+        (self.len() == 1).then(|| self.get(0)).flatten()
+    }
+}
+
+impl fmt::Display for AddressPattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields: Vec<String> = self.fields.iter()
+            .map(|field| {
+                field.iter()
+                    .map(|&(start, end)| match (self.v6, start == end) {
+                        (false, true) => start.to_string(),
+                        (false, false) => format!("{}-{}", start, end),
+                        (true, true) => format!("{:x}", start),
+                        (true, false) => format!("{:x}-{:x}", start, end),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect();
+        f.write_str(&fields.join(if self.v6 { ":" } else { "." }))
+    }
+}
+
+fn field_len(field: &[(u16, u16)]) -> u128 {
+    field.iter().map(|&(start, end)| (end - start) as u128 + 1).sum()
+}
+
+impl TargetSpec {
+    /// First and last address as integers, and whether they are IPv6
+    fn bounds(&self) -> (u128, u128, bool) {
+        match self {
+            TargetSpec::Single(ip) => {
+                let value = ip_to_u128(ip);
+                (value, value, ip.is_ipv6())
+            }
+            TargetSpec::Scoped(ip, _) => {
+                let value = u128::from(*ip);
+                (value, value, true)
+            }
+            TargetSpec::Cidr(IpNet::V4(net)) => {
+                let (first, last) = (u32::from(net.network()), u32::from(net.broadcast()));
+                if net.prefix_len() < 31 {
+                    (first as u128 + 1, last as u128 - 1, false)
+                } else {
+                    (first as u128, last as u128, false)
+                }
+            }
+            TargetSpec::Cidr(IpNet::V6(net)) => (u128::from(net.network()), u128::from(net.broadcast()), true),
+            TargetSpec::Range(start, end) => (ip_to_u128(start), ip_to_u128(end), start.is_ipv6()),
+            TargetSpec::Pattern(_) => unreachable!("patterns are not contiguous"),
+        }
+    }
+
+    /// Number of addresses; saturates for a full IPv6 `::/0`
+    pub fn len(&self) -> u128 {
+        if let TargetSpec::Pattern(pattern) = self {
+            return pattern.len();
+        }
+        let (first, last, _) = self.bounds();
+        (last - first).saturating_add(1)
+    }
+
+    /// A block always holds at least one address
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Address at `index`, without expanding the ones before it
+    pub fn get(&self, index: u128) -> Option<IpAddr> {
+        if let TargetSpec::Pattern(pattern) = self {
+            return pattern.get(index);
+        }
+        let (first, last, v6) = self.bounds();
+        let value = first.checked_add(index).filter(|value| *value <= last)?;
+        Some(u128_to_ip(value, v6))
+    }
+
+    /// Whether `ip` is one of the addresses this block expands to
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        if let TargetSpec::Pattern(pattern) = self {
+            return pattern.contains(ip);
+        }
+        let (first, last, v6) = self.bounds();
+        let value = ip_to_u128(ip);
+        ip.is_ipv6() == v6 && first <= value && value <= last
+    }
+
+    /// The addresses as sorted inclusive integer intervals
+    fn intervals(&self) -> Vec<(u128, u128)> {
+        match self {
+            TargetSpec::Pattern(pattern) => pattern.intervals(),
+            spec => {
+                let (first, last, _) = spec.bounds();
+                vec![(first, last)]
+            }
+        }
+    }
+
+    fn is_ipv6(&self) -> bool {
+        match self {
+            TargetSpec::Single(ip) | TargetSpec::Range(ip, _) => ip.is_ipv6(),
+            TargetSpec::Cidr(net) => matches!(net, IpNet::V6(_)),
+            TargetSpec::Pattern(pattern) => pattern.v6,
+            TargetSpec::Scoped(..) => true,
+        }
+    }
+
+    /// Like [`TargetSpec::intervals`], but including the network and broadcast
+    /// addresses of a CIDR, see [`TargetSpec::covers`]
+    fn cover_intervals(&self) -> Vec<(u128, u128)> {
+        match self {
+            TargetSpec::Cidr(net) => vec![(ip_to_u128(&net.network()), ip_to_u128(&net.broadcast()))],
+            spec => spec.intervals(),
+        }
+    }
+
+    /// Like [`TargetSpec::contains`], but a CIDR covers its network and broadcast
+    /// addresses too, as expected of an exclusion
+    pub fn covers(&self, ip: &IpAddr) -> bool {
+        match self {
+            TargetSpec::Cidr(net) => net.contains(ip),
+            spec => spec.contains(ip),
+        }
+    }
+}
+
+impl fmt::Display for TargetSpec {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetSpec::Single(ip) => write!(f, "{}", ip),
+            TargetSpec::Cidr(net) => write!(f, "{}", net),
+            TargetSpec::Range(start, end) => write!(f, "{}-{}", start, end),
+            TargetSpec::Pattern(pattern) => write!(f, "{}", pattern),
+            TargetSpec::Scoped(ip, zone) => write!(f, "{}%{}", ip, zone),
+        }
+    }
+}
+
+/// Targets composed from single IPs, resolved domains, CIDRs, ranges and patterns.
+///
+/// Addresses are normalised (IPv4-mapped IPv6 addresses become IPv4) and kept as
+/// sorted, disjoint intervals, so overlapping inputs are scanned once and nothing is
+/// expanded up front: memory follows the number of intervals, not addresses.
+/// Iteration is in ascending address order, IPv4 first. The blocks as given are kept
+/// for display, and every IP remembers the hostnames that resolved to it.
+#[derive(Debug, Clone, Default)]
+pub struct TargetSet {
+    specs: Vec<TargetSpec>,
+    /// Interval start (keyed with whether it is IPv6) to inclusive end
+    intervals: BTreeMap<(bool, u128), u128>,
+    hostnames: HashMap<IpAddr, Vec<String>>,
+    /// Asset tags such as an instance ID or inventory group, by IP
+    tags: HashMap<IpAddr, BTreeMap<String, String>>,
+    zones: HashMap<Ipv6Addr, Zone>,
+    /// Intervals in order with the number of addresses before each, built on demand
+    index: OnceLock<Vec<Interval>>,
+    /// Intervals of the blocks with CIDRs in full, built on demand for [`TargetSet::covers`]
+    cover: OnceLock<BTreeMap<(bool, u128), u128>>,
+    /// Intervals of the single addresses and CIDRs, built on demand for [`TargetSet::class_of`]
+    listed: OnceLock<BTreeMap<(bool, u128), u128>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interval {
+    v6: bool,
+    first: u128,
+    last: u128,
+    offset: u128,
+}
+
+impl TargetSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, spec: TargetSpec) {
+        let spec = match spec {
+            TargetSpec::Single(ip) => TargetSpec::Single(ip.to_canonical()),
+            spec => spec,
+        };
+        if let TargetSpec::Scoped(ip, zone) = &spec {
+            self.set_zone(*ip, zone.clone());
+        }
+        let v6 = spec.is_ipv6();
+        for (first, last) in spec.intervals() {
+            self.insert_interval(v6, first, last);
+        }
+        self.specs.push(spec);
+        self.index.take();
+        self.cover.take();
+        self.listed.take();
+    }
+
+    pub fn add_ip(&mut self, ip: IpAddr) {
+        self.add(TargetSpec::Single(ip));
+    }
+
+    /// Add an address `hostname` resolved to, remembering the name for reports
+    pub fn add_host(&mut self, hostname: &str, ip: IpAddr) {
+        self.add_ip(ip);
+        self.record_hostname(ip, hostname);
+    }
+
+    /// Remember that `hostname` resolves to `ip` without scanning the whole address
+    pub fn record_hostname(&mut self, ip: IpAddr, hostname: &str) {
+        let names = self.hostnames.entry(ip.to_canonical()).or_default();
+        if !names.iter().any(|name| name.eq_ignore_ascii_case(hostname)) {
+            names.push(hostname.to_string());
+        }
+    }
+
+    /// Hostnames that resolved to `ip`, in the order they were given
+    pub fn hostnames(&self, ip: &IpAddr) -> &[String] {
+        self.hostnames.get(&ip.to_canonical()).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Tag `ip` with `key=value`; values of the same key given more than once are
+    /// kept as a comma-separated list
+    pub fn add_tag(&mut self, ip: IpAddr, key: &str, value: &str) {
+        let tags = self.tags.entry(ip.to_canonical()).or_default();
+        let existing = tags.entry(key.to_string()).or_default();
+        for item in value.split(',') {
+            if existing.split(',').any(|known| known == item) {
+                continue;
+            }
+            if !existing.is_empty() {
+                existing.push(',');
+            }
+            existing.push_str(item);
+        }
+    }
+
+    /// Asset tags of `ip`, sorted by key
+    pub fn tags(&self, ip: &IpAddr) -> Option<&BTreeMap<String, String>> {
+        self.tags.get(&ip.to_canonical())
+    }
+
+    /// Reach `ip` through the interface `zone`, e.g. for a link-local address
+    pub fn set_zone(&mut self, ip: Ipv6Addr, zone: Zone) {
+        self.zones.insert(ip, zone);
+    }
+
+    /// Interface `ip` is scoped to, if it was given with one
+    pub fn zone(&self, ip: &IpAddr) -> Option<&Zone> {
+        match ip {
+            IpAddr::V6(ip) => self.zones.get(ip),
+            IpAddr::V4(_) => None,
+        }
+    }
+
+    /// The set as inclusive `(first, last)` ranges in iteration order
+    pub fn ranges(&self) -> impl Iterator<Item = (IpAddr, IpAddr)> + '_ {
+        self.intervals.iter().map(|(&(v6, first), &last)| (u128_to_ip(first, v6), u128_to_ip(last, v6)))
+    }
+
+    /// Addresses of the set with a hostname, asset tag or zone ID, in iteration order
+    pub fn annotated_addresses(&self) -> BTreeSet<IpAddr> {
+        self.hostnames.keys().copied()
+            .chain(self.tags.keys().copied())
+            .chain(self.zones.keys().map(|&ip| IpAddr::V6(ip)))
+            .filter(|ip| self.contains(ip))
+            .collect()
+    }
+
+    /// Addresses with at least one recorded hostname, in no particular order
+    pub fn named_addresses(&self) -> impl Iterator<Item = IpAddr> + '_ {
+        self.hostnames.keys().copied()
+    }
+
+    pub fn extend(&mut self, other: TargetSet) {
+        for spec in other.specs {
+            self.add(spec);
+        }
+        for (ip, names) in other.hostnames {
+            for name in names {
+                self.record_hostname(ip, &name);
+            }
+        }
+        for (ip, tags) in other.tags {
+            for (key, value) in tags {
+                self.add_tag(ip, &key, &value);
+            }
+        }
+        self.zones.extend(other.zones);
+    }
+
+    /// Blocks in the order they were added, duplicates included
+    pub fn specs(&self) -> &[TargetSpec] {
+        &self.specs
+    }
+
+    /// Number of distinct addresses
+    pub fn len(&self) -> u128 {
+        self.index().last().map_or(0, |last| last.offset.saturating_add((last.last - last.first).saturating_add(1)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Addresses given more than once across the blocks
+    pub fn duplicates(&self) -> u128 {
+        self.specs.iter()
+            .fold(0u128, |total, spec| total.saturating_add(spec.len()))
+            .saturating_sub(self.len())
+    }
+
+    /// Whether the set holds `ip`
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        within(&self.intervals, ip)
+    }
+
+    /// Whether any block covers `ip`, see [`TargetSpec::covers`]
+    pub fn covers(&self, ip: &IpAddr) -> bool {
+        within(self.cover_intervals(), ip)
+    }
+
+    /// Number of addresses in the set that a block of `exclusions` covers
+    pub fn covered_count(&self, exclusions: &TargetSet) -> u128 {
+        self.overlaps(exclusions).iter()
+            .fold(0u128, |total, &(_, first, last)| total.saturating_add(last - first).saturating_add(1))
+    }
+
+    /// Addresses of the set that a block of `other` covers, see [`TargetSpec::covers`]
+    pub fn intersection(&self, other: &TargetSet) -> TargetSet {
+        TargetSet::from_intervals(self.overlaps(other))
+    }
+
+    /// Addresses of the set that no block of `other` covers, with their hostnames,
+    /// tags and zone IDs
+    pub fn difference(&self, other: &TargetSet) -> TargetSet {
+        let cover = other.cover_intervals();
+        let mut pieces = Vec::new();
+        for (&(v6, first), &last) in &self.intervals {
+            let mut next = Some(first);
+            for (cover_first, cover_last) in overlapping(cover, v6, first, last) {
+                let Some(start) = next else { break };
+                if cover_first > start {
+                    pieces.push((v6, start, cover_first - 1));
+                }
+                next = cover_last.checked_add(1).filter(|&value| value <= last);
+            }
+            if let Some(start) = next {
+                pieces.push((v6, start, last));
+            }
+        }
+        let mut rest = TargetSet::from_intervals(pieces);
+        rest.hostnames = self.hostnames.iter()
+            .filter(|(ip, _)| rest.contains(ip))
+            .map(|(ip, names)| (*ip, names.clone()))
+            .collect();
+        rest.tags = self.tags.iter()
+            .filter(|(ip, _)| rest.contains(ip))
+            .map(|(ip, tags)| (*ip, tags.clone()))
+            .collect();
+        rest.zones = self.zones.iter()
+            .filter(|(ip, _)| rest.contains(&IpAddr::V6(**ip)))
+            .map(|(ip, zone)| (*ip, zone.clone()))
+            .collect();
+        rest
+    }
+
+    /// Addresses of the set outside the classes `policy` skips; network and
+    /// broadcast addresses have no blocks and are kept
+    pub fn without_skipped(&self, policy: &AddressPolicy) -> TargetSet {
+        AddressClass::ALL.iter()
+            .filter(|&&class| class != AddressClass::NetworkBroadcast && policy.skips(Some(class)))
+            .fold(self.clone(), |rest, class| rest.difference(class.blocks()))
+    }
+
+    /// Pieces of the set's intervals inside the blocks of `other`
+    fn overlaps(&self, other: &TargetSet) -> Vec<(bool, u128, u128)> {
+        let mut pieces = Vec::new();
+        for (&(v6, first), &last) in other.cover_intervals() {
+            for (own_first, own_last) in overlapping(&self.intervals, v6, first, last) {
+                pieces.push((v6, own_first.max(first), own_last.min(last)));
+            }
+        }
+        pieces
+    }
+
+    /// Every block's addresses as merged intervals, CIDRs in full
+    fn cover_intervals(&self) -> &BTreeMap<(bool, u128), u128> {
+        self.cover.get_or_init(|| {
+            let mut cover = TargetSet::new();
+            for spec in &self.specs {
+                for (first, last) in spec.cover_intervals() {
+                    cover.insert_interval(spec.is_ipv6(), first, last);
+                }
+            }
+            cover.intervals
+        })
+    }
+
+    fn from_intervals(intervals: Vec<(bool, u128, u128)>) -> TargetSet {
+        let mut set = TargetSet::new();
+        for (v6, first, last) in intervals {
+            let (start, end) = (u128_to_ip(first, v6), u128_to_ip(last, v6));
+            set.add(if first == last { TargetSpec::Single(start) } else { TargetSpec::Range(start, end) });
+        }
+        set
+    }
+
+    /// Number of addresses on the public internet, see [`is_public`]
+    pub fn public_count(&self) -> u128 {
+        self.len() - self.covered_count(non_public())
+    }
+
+    /// Class of `ip` as a target of the set, see [`classify`]. Only `.0` and `.255`
+    /// addresses that come from ranges and patterns are network and broadcast
+    /// addresses: ones named on their own were asked for, and inside a CIDR (which
+    /// drops its own network and broadcast addresses already) they are ordinary hosts.
+    pub fn class_of(&self, ip: &IpAddr) -> Option<AddressClass> {
+        classify(ip).filter(|&class| class != AddressClass::NetworkBroadcast || !within(self.listed_intervals(), ip))
+    }
+
+    /// Number of addresses in each class, see [`TargetSet::class_of`], leaving out
+    /// the addresses a block of `ignore` covers; classes without any address are
+    /// left out too
+    pub fn class_counts(&self, ignore: &TargetSet) -> Vec<(AddressClass, u128)> {
+        let mut counts = Vec::new();
+        let mut rest = self.difference(ignore);
+        for class in AddressClass::ALL {
+            let count = match class {
+                AddressClass::NetworkBroadcast => {
+                    let listed = TargetSet::from_intervals(self.listed_intervals().iter()
+                        .map(|(&(v6, first), &last)| (v6, first, last))
+                        .collect());
+                    rest.difference(&listed).intervals.iter()
+                        .filter(|(&(v6, _), _)| !v6)
+                        .fold(0u128, |total, (&(_, first), &last)| total + subnet_edges(first, last))
+                }
+                _ => {
+                    let count = rest.covered_count(class.blocks());
+                    rest = rest.difference(class.blocks());
+                    count
+                }
+            };
+            if count > 0 {
+                counts.push((class, count));
+            }
+        }
+        counts
+    }
+
+    /// Single addresses and CIDRs (in full) as merged intervals
+    fn listed_intervals(&self) -> &BTreeMap<(bool, u128), u128> {
+        self.listed.get_or_init(|| {
+            let mut listed = TargetSet::new();
+            for spec in &self.specs {
+                if matches!(spec, TargetSpec::Single(_) | TargetSpec::Scoped(..) | TargetSpec::Cidr(_)) {
+                    for (first, last) in spec.cover_intervals() {
+                        listed.insert_interval(spec.is_ipv6(), first, last);
+                    }
+                }
+            }
+            listed.intervals
+        })
+    }
+
+    /// Address at `index` in iteration order
+    pub fn get(&self, index: u128) -> Option<IpAddr> {
+        let index_table = self.index();
+        let position = index_table.partition_point(|interval| interval.offset <= index).checked_sub(1)?;
+        let interval = index_table[position];
+        let value = interval.first.checked_add(index - interval.offset).filter(|value| *value <= interval.last)?;
+        Some(u128_to_ip(value, interval.v6))
+    }
+
+    pub fn iter(&self) -> TargetIterator<'_> {
+        TargetIterator {
+            intervals: self.index(),
+            position: 0,
+            next: None,
+        }
+    }
+
+    fn index(&self) -> &[Interval] {
+        self.index.get_or_init(|| {
+            let mut offset = 0u128;
+            self.intervals.iter()
+                .map(|(&(v6, first), &last)| {
+                    let interval = Interval { v6, first, last, offset };
+                    offset = offset.saturating_add((last - first).saturating_add(1));
+                    interval
+                })
+                .collect()
+        })
+    }
+
+    /// Insert `first..=last`, merging it with any interval it overlaps or touches
+    fn insert_interval(&mut self, v6: bool, mut first: u128, mut last: u128) {
+        if let Some((&(prev_v6, prev_first), &prev_last)) = self.intervals.range(..=(v6, first)).next_back() {
+            if prev_v6 == v6 && prev_last.saturating_add(1) >= first {
+                first = prev_first;
+                last = last.max(prev_last);
+                self.intervals.remove(&(v6, prev_first));
             }
         }
-        IpNet::V6(net) => {
-            for ip in net.hosts() {
-                ips.push(ip.to_string());
+        while let Some((&(next_v6, next_first), &next_last)) = self.intervals.range((v6, first)..).next() {
+            if next_v6 != v6 || next_first > last.saturating_add(1) {
+                break;
             }
+            last = last.max(next_last);
+            self.intervals.remove(&(v6, next_first));
+        }
+        self.intervals.insert((v6, first), last);
+    }
+}
+
+fn non_public() -> &'static TargetSet {
+    static NON_PUBLIC: OnceLock<TargetSet> = OnceLock::new();
+    NON_PUBLIC.get_or_init(|| {
+        NON_PUBLIC_RANGES.iter()
+            .map(|range| parse_cidr(range).expect("valid built-in range"))
+            .fold(TargetSet::new(), |mut set, spec| {
+                set.add(spec);
+                set
+            })
+    })
+}
+
+/// Whether `ip` is outside every block of [`NON_PUBLIC_RANGES`]
+pub fn is_public(ip: &IpAddr) -> bool {
+    !non_public().covers(ip)
+}
+
+/// Kinds of addresses that are rarely meant to be scanned, in the order an
+/// address is checked against them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum AddressClass {
+    Loopback,
+    LinkLocal,
+    Multicast,
+    Documentation,
+    /// Unspecified, IETF protocol, benchmarking, discard and future-use blocks
+    Reserved,
+    /// `.0` and `.255` IPv4 addresses, the network and broadcast addresses of a /24,
+    /// when they come from a range or pattern
+    NetworkBroadcast,
+}
+
+impl AddressClass {
+    pub const ALL: [AddressClass; 6] = [
+        AddressClass::Loopback,
+        AddressClass::LinkLocal,
+        AddressClass::Multicast,
+        AddressClass::Documentation,
+        AddressClass::Reserved,
+        AddressClass::NetworkBroadcast,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AddressClass::Loopback => "loopback",
+            AddressClass::LinkLocal => "link-local",
+            AddressClass::Multicast => "multicast",
+            AddressClass::Documentation => "documentation",
+            AddressClass::Reserved => "reserved",
+            AddressClass::NetworkBroadcast => "network/broadcast",
+        }
+    }
+
+    /// Blocks making up the class; network and broadcast addresses have none
+    fn ranges(&self) -> &'static [&'static str] {
+        match self {
+            AddressClass::Loopback => &["127.0.0.0/8", "::1/128"],
+            AddressClass::LinkLocal => &["169.254.0.0/16", "fe80::/10"],
+            AddressClass::Multicast => &["224.0.0.0/4", "ff00::/8"],
+            AddressClass::Documentation => &["192.0.2.0/24", "198.51.100.0/24", "203.0.113.0/24", "2001:db8::/32", "3fff::/20"],
+            AddressClass::Reserved => &["0.0.0.0/8", "192.0.0.0/24", "198.18.0.0/15", "240.0.0.0/4", "::/128", "100::/64", "2001:2::/48"],
+            AddressClass::NetworkBroadcast => &[],
+        }
+    }
+
+    fn blocks(&self) -> &'static TargetSet {
+        static BLOCKS: OnceLock<Vec<TargetSet>> = OnceLock::new();
+        let blocks = BLOCKS.get_or_init(|| {
+            AddressClass::ALL.iter()
+                .map(|class| class.ranges().iter()
+                    .map(|range| parse_cidr(range).expect("valid built-in range"))
+                    .fold(TargetSet::new(), |mut set, spec| {
+                        set.add(spec);
+                        set
+                    }))
+                .collect()
+        });
+        &blocks[*self as usize]
+    }
+}
+
+impl fmt::Display for AddressClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for AddressClass {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "loopback" => Ok(AddressClass::Loopback),
+            "link-local" | "linklocal" => Ok(AddressClass::LinkLocal),
+            "multicast" => Ok(AddressClass::Multicast),
+            "documentation" | "doc" => Ok(AddressClass::Documentation),
+            "reserved" => Ok(AddressClass::Reserved),
+            "network" | "broadcast" | "network/broadcast" => Ok(AddressClass::NetworkBroadcast),
+            _ => Err(format!("Unknown address class: {} (use loopback, link-local, multicast, documentation, reserved or network)", value)),
+        }
+    }
+}
+
+/// Class of `ip`, if it belongs to one, judging `.0` and `.255` IPv4 addresses as
+/// network and broadcast addresses
+pub fn classify(ip: &IpAddr) -> Option<AddressClass> {
+    let ip = ip.to_canonical();
+    AddressClass::ALL.into_iter()
+        .find(|class| class.blocks().covers(&ip))
+        .or_else(|| is_subnet_edge(&ip).then_some(AddressClass::NetworkBroadcast))
+}
+
+fn is_subnet_edge(ip: &IpAddr) -> bool {
+    matches!(ip, IpAddr::V4(ip) if matches!(ip.octets()[3], 0 | 255))
+}
+
+/// Number of `.0` and `.255` IPv4 addresses in `first..=last`
+fn subnet_edges(first: u128, last: u128) -> u128 {
+    let upto = |value: u128| value / 256 + 1 + (value + 1) / 256;
+    upto(last) - first.checked_sub(1).map_or(0, upto)
+}
+
+/// What to do with the targets of an [`AddressClass`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassAction {
+    /// Report as skipped without connecting
+    Skip,
+    /// Scan, with a warning before the scan starts
+    Warn,
+    /// Scan without comment
+    Allow,
+}
+
+impl ClassAction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ClassAction::Skip => "skip",
+            ClassAction::Warn => "warn",
+            ClassAction::Allow => "allow",
+        }
+    }
+}
+
+impl FromStr for ClassAction {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "skip" => Ok(ClassAction::Skip),
+            "warn" => Ok(ClassAction::Warn),
+            "allow" | "scan" => Ok(ClassAction::Allow),
+            _ => Err(format!("Unknown action: {} (use skip, warn or allow)", value)),
+        }
+    }
+}
+
+/// Action taken for each [`AddressClass`].
+///
+/// By default network/broadcast, multicast, reserved and documentation addresses are
+/// skipped, and loopback and link-local ones scanned with a warning. Written as
+/// `CLASS=ACTION,...` on top of the defaults, where `all` names every class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AddressPolicy {
+    actions: [ClassAction; 6],
+}
+
+impl Default for AddressPolicy {
+    fn default() -> Self {
+        let mut policy = AddressPolicy { actions: [ClassAction::Skip; 6] };
+        policy.set(AddressClass::Loopback, ClassAction::Warn);
+        policy.set(AddressClass::LinkLocal, ClassAction::Warn);
+        policy
+    }
+}
+
+impl AddressPolicy {
+    /// Every class scanned without comment
+    pub fn allow_all() -> Self {
+        AddressPolicy { actions: [ClassAction::Allow; 6] }
+    }
+
+    pub fn action(&self, class: AddressClass) -> ClassAction {
+        self.actions[class as usize]
+    }
+
+    pub fn set(&mut self, class: AddressClass, action: ClassAction) {
+        self.actions[class as usize] = action;
+    }
+
+    /// Whether targets of `class`, if any, are skipped
+    pub fn skips(&self, class: Option<AddressClass>) -> bool {
+        class.is_some_and(|class| self.action(class) == ClassAction::Skip)
+    }
+}
+
+impl FromStr for AddressPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut policy = AddressPolicy::default();
+        for item in value.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (class, action) = item.split_once('=')
+                .ok_or_else(|| format!("Invalid address policy {}: expected CLASS=ACTION, e.g. multicast=warn", item))?;
+            let action: ClassAction = action.parse()?;
+            if class.trim().eq_ignore_ascii_case("all") {
+                policy.actions = [action; 6];
+            } else {
+                policy.set(class.parse()?, action);
+            }
+        }
+        Ok(policy)
+    }
+}
+
+/// Whether one of `intervals` holds `ip`
+fn within(intervals: &BTreeMap<(bool, u128), u128>, ip: &IpAddr) -> bool {
+    let ip = ip.to_canonical();
+    let value = ip_to_u128(&ip);
+    intervals.range(..=(ip.is_ipv6(), value))
+        .next_back()
+        .is_some_and(|(&(v6, _), &last)| v6 == ip.is_ipv6() && value <= last)
+}
+
+/// Intervals of `intervals` overlapping `first..=last` of the same IP version, in order
+fn overlapping(intervals: &BTreeMap<(bool, u128), u128>, v6: bool, first: u128, last: u128) -> impl Iterator<Item = (u128, u128)> + '_ {
+    // Start from the interval reaching into the range, if any
+    let start = intervals.range(..=(v6, first))
+        .next_back()
+        .filter(|(&(prev_v6, _), &prev_last)| prev_v6 == v6 && prev_last >= first)
+        .map_or((v6, first), |(&key, _)| key);
+    intervals.range(start..)
+        .take_while(move |(&(other_v6, other_first), _)| other_v6 == v6 && other_first <= last)
+        .map(|(&(_, other_first), &other_last)| (other_first, other_last))
+}
+
+impl<'a> IntoIterator for &'a TargetSet {
+    type Item = IpAddr;
+    type IntoIter = TargetIterator<'a>;
+
+    fn into_iter(self) -> TargetIterator<'a> {
+        self.iter()
+    }
+}
+
+impl FromIterator<IpAddr> for TargetSet {
+    fn from_iter<I: IntoIterator<Item = IpAddr>>(ips: I) -> Self {
+        let mut targets = TargetSet::new();
+        for ip in ips {
+            targets.add_ip(ip);
+        }
+        targets
+    }
+}
+
+/// Streams the addresses of a [`TargetSet`] one at a time
+#[derive(Debug, Clone)]
+pub struct TargetIterator<'a> {
+    intervals: &'a [Interval],
+    position: usize,
+    /// Next value within the current interval
+    next: Option<u128>,
+}
+
+impl Iterator for TargetIterator<'_> {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<IpAddr> {
+        let interval = self.intervals.get(self.position)?;
+        let value = self.next.unwrap_or(interval.first);
+        if value >= interval.last {
+            self.position += 1;
+            self.next = None;
+        } else {
+            self.next = Some(value + 1);
         }
+        Some(u128_to_ip(value, interval.v6))
     }
-    Ok(ips)
+}
+
+/// Parse CIDR notation into a lazily expanded block of host addresses
+pub fn parse_cidr(cidr_str: &str) -> Result<TargetSpec, String> {
+    let network: IpNet = cidr_str.trim().parse()
+        .map_err(|_| format!("Invalid CIDR notation: {}", cidr_str))?;
+    Ok(TargetSpec::Cidr(network))
 }
 
 /// Parse IP range (e.g., "192.168.1.1-192.168.1.254")
-pub fn parse_ip_range(range_str: &str) -> Result<Vec<String>, String> {
+pub fn parse_ip_range(range_str: &str) -> Result<TargetSpec, String> {
     let parts: Vec<&str> = range_str.split('-').collect();
     if parts.len() != 2 {
         return Err(format!("Invalid IP range format: {}", range_str));
@@ -34,43 +980,358 @@ pub fn parse_ip_range(range_str: &str) -> Result<Vec<String>, String> {
     let end_ip: IpAddr = parts[1].trim().parse()
         .map_err(|_| format!("Invalid end IP: {}", parts[1]))?;
     
-    let mut ips = Vec::new();
+    if start_ip.is_ipv4() != end_ip.is_ipv4() {
+        return Err("Start and end IPs must be of the same version (IPv4 or IPv6)".to_string());
+    }
+    if ip_to_u128(&start_ip) > ip_to_u128(&end_ip) {
+        return Err("Start IP must be less than or equal to end IP".to_string());
+    }
     
-    match (start_ip, end_ip) {
-        (IpAddr::V4(start), IpAddr::V4(end)) => {
-            let start_u32 = u32::from(start);
-            let end_u32 = u32::from(end);
-            
-            if start_u32 > end_u32 {
-                return Err("Start IP must be less than or equal to end IP".to_string());
-            }
-            
-            for ip_u32 in start_u32..=end_u32 {
-                ips.push(Ipv4Addr::from(ip_u32).to_string());
+    Ok(TargetSpec::Range(start_ip, end_ip))
+}
+
+/// Parse a single target: an IP, a CIDR, a `start-end` range or an nmap-style
+/// pattern giving each IPv4 octet (or IPv6 group) as `*`, a value, a `a-b` range
+/// (either end may be left open) or a comma-separated list of those, e.g.
+/// `192.168.1-3.1-254`, `10.0.0,2,4.*` or `2001:db8::1-ff`
+pub fn parse_target(target: &str) -> Result<TargetSpec, String> {
+    let target = target.trim();
+    if target.is_empty() {
+        return Err("Empty target".to_string());
+    }
+    if let Ok(ip) = target.parse::<IpAddr>() {
+        return Ok(TargetSpec::Single(ip));
+    }
+    if target.contains('%') {
+        return parse_scoped(target);
+    }
+    if target.contains('/') {
+        return parse_cidr(target);
+    }
+    if let Some((start, end)) = target.split_once('-') {
+        if start.trim().parse::<IpAddr>().is_ok() && end.trim().parse::<IpAddr>().is_ok() {
+            return parse_ip_range(target);
+        }
+    }
+    let pattern = parse_pattern(target)?;
+    Ok(match pattern.single() {
+        Some(ip) => TargetSpec::Single(ip),
+        None => TargetSpec::Pattern(pattern),
+    })
+}
+
+/// Parse an IPv6 address with a zone ID, `fe80::1%eth0` or `fe80::1%2`
+pub fn parse_scoped(target: &str) -> Result<TargetSpec, String> {
+    let (ip, zone) = target.trim().split_once('%')
+        .ok_or_else(|| format!("Missing zone ID in {}", target))?;
+    let ip = ip.parse::<Ipv6Addr>()
+        .map_err(|_| format!("Invalid target {}: only IPv6 addresses take a zone ID", target))?;
+    Ok(TargetSpec::Scoped(ip, parse_zone(zone)?))
+}
+
+/// Resolve a zone ID given as an interface index or, on Unix, an interface name
+pub fn parse_zone(zone: &str) -> Result<Zone, String> {
+    if zone.is_empty() {
+        return Err("Empty zone ID".to_string());
+    }
+    let id = match zone.parse::<u32>() {
+        Ok(id) => id,
+        Err(_) => interface_index(zone)?,
+    };
+    Ok(Zone { id, name: zone.to_string() })
+}
+
+#[cfg(unix)]
+fn interface_index(name: &str) -> Result<u32, String> {
+    let unknown = || format!("Unknown network interface {}", name);
+    let name = std::ffi::CString::new(name).map_err(|_| unknown())?;
+    // SAFETY: `name` is a valid NUL-terminated string for the duration of the call
+    match unsafe { libc::if_nametoindex(name.as_ptr()) } {
+        0 => Err(unknown()),
+        id => Ok(id),
+    }
+}
+
+#[cfg(not(unix))]
+fn interface_index(name: &str) -> Result<u32, String> {
+    Err(format!("Zone ID {} must be a numeric interface index on this platform", name))
+}
+
+/// A subnet attached to one of this machine's network interfaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalSubnet {
+    pub interface: String,
+    /// Address of the interface on this subnet
+    pub address: IpAddr,
+    /// Prefix length configured on the interface
+    pub prefix_len: u8,
+    /// The block to scan, narrowed when `prefix_len` is broader than the cap
+    pub network: IpNet,
+}
+
+impl LocalSubnet {
+    /// Whether the configured subnet was too large and only part of it is scanned
+    pub fn is_narrowed(&self) -> bool {
+        self.network.prefix_len() != self.prefix_len
+    }
+
+    pub fn to_target(&self) -> TargetSpec {
+        TargetSpec::Cidr(self.network)
+    }
+}
+
+/// Subnets attached to the local interfaces, or only to `interface` when given.
+///
+/// Loopback is skipped unless its interface is named explicitly. IPv6 link-local
+/// prefixes are skipped as well, since they are not routable without a zone ID.
+/// Prefixes broader than [`LOCAL_SUBNET_MIN_PREFIX_V4`] or
+/// [`LOCAL_SUBNET_MIN_PREFIX_V6`] are narrowed to that size around the interface
+/// address.
+pub fn local_subnets(interface: Option<&str>) -> Result<Vec<LocalSubnet>, String> {
+    let interfaces = if_addrs::get_if_addrs()
+        .map_err(|e| format!("Failed to list network interfaces: {}", e))?;
+    if let Some(name) = interface {
+        if !interfaces.iter().any(|iface| iface.name == name) {
+            return Err(format!("Unknown network interface {}", name));
+        }
+    }
+    let mut subnets: Vec<LocalSubnet> = Vec::new();
+    for iface in interfaces {
+        match interface {
+            Some(name) if iface.name != name => continue,
+            None if iface.is_loopback() => continue,
+            _ => {}
+        }
+        let (address, prefix_len, min_prefix) = match &iface.addr {
+            if_addrs::IfAddr::V4(addr) => (IpAddr::V4(addr.ip), addr.prefixlen, LOCAL_SUBNET_MIN_PREFIX_V4),
+            if_addrs::IfAddr::V6(addr) if addr.ip.is_unicast_link_local() => continue,
+            if_addrs::IfAddr::V6(addr) => (IpAddr::V6(addr.ip), addr.prefixlen, LOCAL_SUBNET_MIN_PREFIX_V6),
+        };
+        let network = IpNet::new(address, prefix_len.max(min_prefix))
+            .map_err(|e| format!("Invalid prefix on interface {}: {}", iface.name, e))?
+            .trunc();
+        if !subnets.iter().any(|subnet| subnet.network == network) {
+            subnets.push(LocalSubnet { interface: iface.name, address, prefix_len, network });
+        }
+    }
+    Ok(subnets)
+}
+
+/// Parse a whitespace or comma separated list of targets (see [`parse_target`]).
+/// A comma continues the current octet when what follows cannot start a new
+/// address, so `10.0.0,2,4.*,10.1.0.1` holds two targets.
+pub fn parse_target_list(list: &str) -> Result<TargetSet, String> {
+    let mut targets = TargetSet::new();
+    for target in split_target_list(list) {
+        targets.add(parse_target(&target)?);
+    }
+    Ok(targets)
+}
+
+/// Split a target list into individual targets, keeping octet value lists together
+pub(crate) fn split_target_list(list: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for token in list.split_whitespace() {
+        let mut pieces = token.split(',').filter(|piece| !piece.is_empty());
+        let Some(first) = pieces.next() else { continue };
+        let mut current = first.to_string();
+        for piece in pieces {
+            let v6 = current.contains(':');
+            let fields_done = current.matches(if v6 { ':' } else { '.' }).count();
+            let continues = if v6 {
+                !piece.contains(':')
+            } else {
+                // Still inside the octets, or more values for the last octet
+                current.chars().all(|c| c.is_ascii_digit() || ".-*,".contains(c))
+                    && (fields_done < 3 || !piece.contains('.'))
+                    && !piece.contains(':')
+                    && !piece.contains('/')
+            };
+            if continues {
+                current.push(',');
+                current.push_str(piece);
+            } else {
+                targets.push(std::mem::replace(&mut current, piece.to_string()));
             }
         }
-        (IpAddr::V6(start), IpAddr::V6(end)) => {
-            let start_u128 = u128::from(start);
-            let end_u128 = u128::from(end);
-            
-            if start_u128 > end_u128 {
-                return Err("Start IP must be less than or equal to end IP".to_string());
+        targets.push(current);
+    }
+    targets
+}
+
+/// Parse an nmap-style octet (IPv4) or group (IPv6) pattern
+fn parse_pattern(target: &str) -> Result<AddressPattern, String> {
+    let v6 = target.contains(':');
+    let fields: Vec<&str> = if v6 {
+        let mut halves = target.split("::");
+        let head = halves.next().unwrap_or_default();
+        match (halves.next(), halves.next()) {
+            (None, _) => head.split(':').collect(),
+            (Some(tail), None) => {
+                let head: Vec<&str> = if head.is_empty() { Vec::new() } else { head.split(':').collect() };
+                let tail: Vec<&str> = if tail.is_empty() { Vec::new() } else { tail.split(':').collect() };
+                if head.len() + tail.len() > 7 {
+                    return Err(format!("Invalid target {}: too many groups around \"::\"", target));
+                }
+                let zeros = vec!["0"; 8 - head.len() - tail.len()];
+                head.into_iter().chain(zeros).chain(tail).collect()
             }
-            
-            // For IPv6, we limit the range to prevent excessive memory usage
-            let diff = end_u128.saturating_sub(start_u128);
-            if diff > 65536 {
-                return Err("IPv6 range too large (max 65536 addresses)".to_string());
+            _ => return Err(format!("Invalid target {}: \"::\" may only appear once", target)),
+        }
+    } else {
+        target.split('.').collect()
+    };
+
+    let (expected, unit, max) = if v6 { (8, "group", 0xffff) } else { (4, "octet", 255) };
+    if fields.len() != expected {
+        return Err(format!("Invalid target {}: expected {} {}s, found {}", target, expected, unit, fields.len()));
+    }
+
+    let mut parsed = Vec::with_capacity(expected);
+    for (position, field) in fields.iter().enumerate() {
+        let location = format!("{} {}", unit, position + 1);
+        let mut ranges = Vec::new();
+        for item in field.split(',') {
+            if item.is_empty() {
+                return Err(format!("Invalid target {}: empty value in {}", target, location));
             }
-            
-            for ip_u128 in start_u128..=end_u128 {
-                ips.push(Ipv6Addr::from(ip_u128).to_string());
+            let value = |token: &str, default: u16| -> Result<u16, String> {
+                if token.is_empty() {
+                    return Ok(default);
+                }
+                let parsed = if v6 { u32::from_str_radix(token, 16).ok() } else { token.parse::<u32>().ok() };
+                parsed.filter(|value| *value <= max as u32)
+                    .map(|value| value as u16)
+                    .ok_or_else(|| if v6 {
+                        format!("Invalid target {}: \"{}\" in {} is not a hex value between 0 and ffff", target, token, location)
+                    } else {
+                        format!("Invalid target {}: \"{}\" in {} is not a number between 0 and 255", target, token, location)
+                    })
+            };
+            let range = match item.split_once('-') {
+                _ if item == "*" => (0, max),
+                Some((start, end)) => {
+                    let range = (value(start, 0)?, value(end, max)?);
+                    if range.0 > range.1 {
+                        return Err(format!("Invalid target {}: range \"{}\" in {} is reversed", target, item, location));
+                    }
+                    range
+                }
+                None => {
+                    let single = value(item, 0)?;
+                    (single, single)
+                }
+            };
+            ranges.push(range);
+        }
+        // Merge overlapping values so no address is produced twice
+        ranges.sort_unstable();
+        let mut merged: Vec<(u16, u16)> = Vec::with_capacity(ranges.len());
+        for (start, end) in ranges {
+            match merged.last_mut() {
+                Some(last) if start as u32 <= last.1 as u32 + 1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
             }
         }
-        _ => return Err("Start and end IPs must be of the same version (IPv4 or IPv6)".to_string()),
+        parsed.push(merged);
     }
-    
-    Ok(ips)
+    let pattern = AddressPattern { v6, fields: parsed };
+    if pattern.fragments() > MAX_PATTERN_FRAGMENTS {
+        return Err(format!(
+            "Invalid target {}: the pattern splits into {} separate ranges (at most {}); use CIDRs or fewer value lists",
+            target, pattern.fragments(), MAX_PATTERN_FRAGMENTS
+        ));
+    }
+    Ok(pattern)
+}
+
+/// Read IPs, CIDRs and ranges from a file, one or more per line; `#` starts a comment
+pub fn load_target_file(path: &str) -> Result<TargetSet, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let mut targets = TargetSet::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let parsed = parse_target_list(line)
+            .map_err(|e| format!("{}:{}: {}", path, number + 1, e))?;
+        targets.extend(parsed);
+    }
+    Ok(targets)
+}
+
+/// One line of a target list given with `-iL`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEntry {
+    /// An IP, CIDR or range
+    Spec(TargetSpec),
+    /// A hostname still to be resolved
+    Hostname(String),
+    /// A single port on one host, which is an IP or a hostname (`[v6]:port` for IPv6)
+    Pair(String, u16),
+}
+
+/// Parse one target list entry: an IP, hostname, CIDR, range, nmap-style pattern
+/// or `host:port`
+pub fn parse_input_entry(entry: &str) -> Result<InputEntry, String> {
+    let entry = entry.trim();
+    if let Ok(ip) = entry.parse::<IpAddr>() {
+        return Ok(InputEntry::Spec(TargetSpec::Single(ip)));
+    }
+    if let Some(rest) = entry.strip_prefix('[') {
+        // [2001:db8::1]:443
+        let (host, port) = rest.split_once("]:")
+            .ok_or_else(|| format!("Invalid IPv6 host:port entry: {}", entry))?;
+        if host.contains('%') {
+            parse_scoped(host)?;
+        } else {
+            host.parse::<IpAddr>().map_err(|_| format!("Invalid IPv6 address: {}", host))?;
+        }
+        return Ok(InputEntry::Pair(host.to_string(), parse_entry_port(port, entry)?));
+    }
+    let ipv4_like = entry.chars().all(|c| c.is_ascii_digit() || ".,-*/".contains(c));
+    if entry.matches(':').count() > 1 || ipv4_like || entry.contains('/') {
+        return parse_target(entry).map(InputEntry::Spec);
+    }
+    if let Some((host, port)) = entry.split_once(':') {
+        if !is_valid_hostname(host) && host.parse::<IpAddr>().is_err() {
+            return Err(format!("Invalid host in host:port entry: {}", entry));
+        }
+        return Ok(InputEntry::Pair(host.to_string(), parse_entry_port(port, entry)?));
+    }
+    if is_valid_hostname(entry) {
+        return Ok(InputEntry::Hostname(entry.to_string()));
+    }
+    Err(format!("Not an IP, hostname, CIDR, range or host:port: {}", entry))
+}
+
+/// Read target list entries one per line; blank lines and `#` comments are ignored
+pub fn read_input_list<R: BufRead>(reader: R, source: &str) -> Result<Vec<InputEntry>, String> {
+    let mut entries = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("Failed to read {}: {}", source, e))?;
+        let entry = line.split('#').next().unwrap_or_default().trim();
+        if entry.is_empty() {
+            continue;
+        }
+        entries.push(parse_input_entry(entry).map_err(|e| format!("{}:{}: {}", source, number + 1, e))?);
+    }
+    Ok(entries)
+}
+
+fn parse_entry_port(port: &str, entry: &str) -> Result<u16, String> {
+    port.parse().map_err(|_| format!("Invalid port in host:port entry: {}", entry))
+}
+
+fn is_valid_hostname(host: &str) -> bool {
+    !host.is_empty()
+        && host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
 }
 
 /// Check if an IP address is valid (supports both IPv4 and IPv6)
@@ -78,3 +1339,17 @@ pub fn is_valid_ip(ip_str: &str) -> bool {
     ip_str.parse::<IpAddr>().is_ok()
 }
 
+fn ip_to_u128(ip: &IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(ip) => u32::from(*ip) as u128,
+        IpAddr::V6(ip) => u128::from(*ip),
+    }
+}
+
+fn u128_to_ip(value: u128, v6: bool) -> IpAddr {
+    if v6 {
+        IpAddr::V6(Ipv6Addr::from(value))
+    } else {
+        IpAddr::V4(Ipv4Addr::from(value as u32))
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Largest scan space that can be visited in random order; bigger spaces are
+/// walked sequentially
+pub const MAX_PERMUTED: u128 = 1 << 62;
+
+/// Visits every index in `0..len` exactly once in an order derived from a seed.
+///
+/// Like zmap, this walks the multiplicative group of integers modulo a prime `p`
+/// just above `len`: starting from a random element, each step multiplies by a
+/// primitive root `g`, which cycles through all of `1..p` before returning to the
+/// start. Values past the end of the range are skipped. `p` is a safe prime
+/// (`p = 2q + 1`), so checking a candidate root only needs `g^2` and `g^q`. Only
+/// the current element is kept, however large the range.
+#[derive(Debug, Clone)]
+pub struct CyclicPermutation {
+    len: u64,
+    prime: u64,
+    generator: u64,
+    first: u64,
+    current: u64,
+    started: bool,
+    finished: bool,
+}
+
+impl CyclicPermutation {
+    /// Permutation of `0..len` for `seed`; `None` when `len` exceeds [`MAX_PERMUTED`]
+    pub fn new(len: u128, seed: u64) -> Option<Self> {
+        if len > MAX_PERMUTED {
+            return None;
+        }
+        let len = len as u64;
+        let prime = next_safe_prime(len + 1);
+        let mut state = seed;
+        // Start the search for a primitive root at a seeded point in 2..p-1
+        let mut generator = 2 + splitmix64(&mut state) % (prime - 3).max(1);
+        while !is_primitive_root(generator, prime) {
+            generator = if generator + 1 >= prime - 1 { 2 } else { generator + 1 };
+        }
+        let first = 1 + splitmix64(&mut state) % (prime - 1);
+        Some(CyclicPermutation {
+            len,
+            prime,
+            generator,
+            first,
+            current: first,
+            started: false,
+            finished: false,
+        })
+    }
+}
+
+impl Iterator for CyclicPermutation {
+    type Item = u128;
+
+    fn next(&mut self) -> Option<u128> {
+        while !self.finished {
+            if self.started {
+                self.current = mul_mod(self.current, self.generator, self.prime);
+                if self.current == self.first {
+                    self.finished = true;
+                    return None;
+                }
+            }
+            self.started = true;
+            let index = self.current - 1;
+            if index < self.len {
+                return Some(index as u128);
+            }
+        }
+        None
+    }
+}
+
+impl std::iter::FusedIterator for CyclicPermutation {}
+
+/// Fresh seed for a scan that was not given one
+pub fn random_seed() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e3779b97f4a7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+fn mul_mod(a: u64, b: u64, m: u64) -> u64 {
+    ((a as u128 * b as u128) % m as u128) as u64
+}
+
+fn pow_mod(mut base: u64, mut exp: u64, m: u64) -> u64 {
+    let mut result = 1 % m;
+    base %= m;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, m);
+        }
+        base = mul_mod(base, base, m);
+        exp >>= 1;
+    }
+    result
+}
+
+/// Deterministic Miller-Rabin; these bases are exact for every 64-bit value
+fn is_prime(n: u64) -> bool {
+    const BASES: [u64; 12] = [2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37];
+    if n < 2 {
+        return false;
+    }
+    for &p in &BASES {
+        if n.is_multiple_of(p) {
+            return n == p;
+        }
+    }
+    let mut d = n - 1;
+    let mut s = 0;
+    while d.is_multiple_of(2) {
+        d /= 2;
+        s += 1;
+    }
+    'bases: for &a in &BASES {
+        let mut x = pow_mod(a, d, n);
+        if x == 1 || x == n - 1 {
+            continue;
+        }
+        for _ in 1..s {
+            x = mul_mod(x, x, n);
+            if x == n - 1 {
+                continue 'bases;
+            }
+        }
+        return false;
+    }
+    true
+}
+
+/// Smallest prime `p >= min` (and at least 5) with `(p - 1) / 2` also prime
+fn next_safe_prime(min: u64) -> u64 {
+    let mut q = (min.max(5) - 1) / 2;
+    loop {
+        let p = 2 * q + 1;
+        if p >= min.max(5) && is_prime(q) && is_prime(p) {
+            return p;
+        }
+        q += 1;
+    }
+}
+
+fn is_primitive_root(g: u64, p: u64) -> bool {
+    let q = (p - 1) / 2;
+    pow_mod(g, 2, p) != 1 && pow_mod(g, q, p) != 1
+}
@@ -0,0 +1,339 @@
+use crate::network::{AddressClass, AddressPolicy, ClassAction, TargetSet};
+use serde::ser::{SerializeStruct, Serializer};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+
+/// Time assumed for a probe that gets an answer, when estimating the fastest case
+pub const ASSUMED_ANSWER_TIME: Duration = Duration::from_millis(50);
+
+/// What a scan would do, worked out without opening a single socket
+#[derive(Debug, Clone)]
+pub struct ScanPlan {
+    /// Target addresses left after exclusions, the scope and the classes the
+    /// address policy skips, with their hostnames, tags and zone IDs
+    pub targets: TargetSet,
+    /// Addresses of `targets` probed on every port: network and broadcast addresses
+    /// the policy skips stay inside their ranges but are not counted
+    pub hosts: u128,
+    /// Ports probed on every host
+    pub ports: Vec<u16>,
+    /// Ports of the scan left out by `--exclude-ports`
+    pub excluded_ports: Vec<u16>,
+    /// Host/port pairs probed on top of the target x port grid
+    pub extra_pairs: Vec<(IpAddr, u16)>,
+    /// Every pair in the scan, skipped ones included
+    pub total_pairs: u128,
+    /// Pairs reported as skipped because of exclusions, the scope or the address policy
+    pub skipped: u128,
+    pub concurrency: usize,
+    /// Probes in flight at the start; below `concurrency` while the congestion
+    /// window ramps up
+    pub initial_window: usize,
+    pub connect_timeout: Duration,
+    pub max_retries: u32,
+    pub max_rate: Option<f64>,
+    pub scan_delay: Duration,
+    pub max_per_host: Option<usize>,
+    /// Seed of the random scan order, `None` for a sequential scan
+    pub seed: Option<u64>,
+    /// Target addresses the scope does not permit
+    pub out_of_scope: u128,
+    /// Probed target addresses on the public internet
+    pub public_hosts: u128,
+    /// Target addresses left after exclusions and the scope, by address class
+    pub special_addresses: Vec<(AddressClass, u128)>,
+    pub address_policy: AddressPolicy,
+}
+
+impl ScanPlan {
+    /// Pairs that will actually be probed
+    pub fn probes(&self) -> u128 {
+        self.total_pairs - self.skipped
+    }
+
+    /// Addresses of `special_addresses` the address policy gives `action`
+    pub fn special_count(&self, action: ClassAction) -> u128 {
+        self.special_addresses.iter()
+            .filter(|(class, _)| self.address_policy.action(*class) == action)
+            .fold(0u128, |total, (_, count)| total.saturating_add(*count))
+    }
+
+    /// Expected duration when every probe is answered quickly
+    pub fn fastest(&self) -> Duration {
+        self.duration(ASSUMED_ANSWER_TIME, 1)
+    }
+
+    /// Expected duration when every probe times out and is retried as often as allowed
+    pub fn slowest(&self) -> Duration {
+        self.duration(self.connect_timeout, 1 + self.max_retries)
+    }
+
+    /// The tightest of the limits on throughput: open connections, the rate cap,
+    /// and the delay and connection cap applied to each host. Open connections
+    /// start at the initial window, doubling each round until they reach `concurrency`.
+    fn duration(&self, probe_time: Duration, attempts: u32) -> Duration {
+        let attempts = attempts as f64;
+        let total = self.probes() as f64 * attempts;
+        let concurrency = self.concurrency.max(1) as f64;
+        let (mut window, mut remaining, mut rounds) = (self.initial_window.max(1) as f64, total, 0.0);
+        while window < concurrency && remaining > 0.0 {
+            remaining -= window.min(remaining);
+            rounds += 1.0;
+            window = (window * 2.0).min(concurrency);
+        }
+        let mut seconds = (rounds + remaining / concurrency) * probe_time.as_secs_f64();
+        if let Some(rate) = self.max_rate {
+            seconds = seconds.max(total / rate);
+        }
+        let per_host = self.ports.len() as f64 * attempts;
+        seconds = seconds.max(per_host * self.scan_delay.as_secs_f64());
+        if let Some(limit) = self.max_per_host {
+            seconds = seconds.max(per_host * probe_time.as_secs_f64() / limit.max(1) as f64);
+        }
+        Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+    }
+
+    /// Human-readable plan; only the first `target_limit` target ranges are listed when given
+    pub fn to_text(&self, target_limit: Option<usize>) -> String {
+        let mut text = Vec::new();
+        self.write_text(&mut text, target_limit).expect("writing to memory cannot fail");
+        String::from_utf8(text).expect("the plan is valid UTF-8")
+    }
+
+    /// Write the human-readable plan; targets are listed as merged ranges, each
+    /// followed by its addresses that carry hostnames, tags or zone IDs
+    pub fn write_text<W: Write>(&self, mut writer: W, target_limit: Option<usize>) -> Result<(), String> {
+        let error = |e: std::io::Error| format!("Failed to write the scan plan: {}", e);
+        let mut header = format!("Hosts:        {}", self.hosts);
+        if self.out_of_scope > 0 {
+            header.push_str(&format!(" ({} out of scope)", self.out_of_scope));
+        }
+        if self.public_hosts > 0 {
+            header.push_str(&format!(" ({} public)", self.public_hosts));
+        }
+        writeln!(writer, "{}", header).map_err(error)?;
+        if self.excluded_ports.is_empty() {
+            writeln!(writer, "Ports:        {}", self.ports.len()).map_err(error)?;
+        } else {
+            writeln!(writer, "Ports:        {} ({} excluded)", self.ports.len(), self.excluded_ports.len()).map_err(error)?;
+        }
+        if !self.extra_pairs.is_empty() {
+            writeln!(writer, "Extra pairs:  {}", self.extra_pairs.len()).map_err(error)?;
+        }
+        let mut probes = format!("Total probes: {}", self.probes());
+        if self.skipped > 0 {
+            probes.push_str(&format!(" ({} excluded, out-of-scope or special pairs skipped)", self.skipped));
+        }
+        writeln!(writer, "{}", probes).map_err(error)?;
+        if !self.special_addresses.is_empty() {
+            let classes: Vec<String> = self.special_addresses.iter()
+                .map(|(class, count)| format!("{} {} ({})", count, class, self.address_policy.action(*class).name()))
+                .collect();
+            writeln!(writer, "Special:      {}", classes.join(", ")).map_err(error)?;
+        }
+        if self.initial_window < self.concurrency {
+            writeln!(writer, "Concurrency:  {} (starting at {})", self.concurrency, self.initial_window).map_err(error)?;
+        } else {
+            writeln!(writer, "Concurrency:  {}", self.concurrency).map_err(error)?;
+        }
+        writeln!(writer, "Timeout:      {} ms, {} retries", self.connect_timeout.as_millis(), self.max_retries).map_err(error)?;
+        if let Some(rate) = self.max_rate {
+            writeln!(writer, "Max rate:     {} probes/s", rate).map_err(error)?;
+        }
+        writeln!(writer, "Order:        {}", self.seed.map_or("sequential".to_string(), |seed| format!("random (seed {})", seed))).map_err(error)?;
+        writeln!(writer, "Estimate:     {} if every port answers, up to {} if every port times out",
+            format_duration(self.fastest()), format_duration(self.slowest())).map_err(error)?;
+
+        writeln!(writer, "\nPorts:\n  {}", self.ports.iter().map(u16::to_string).collect::<Vec<_>>().join(",")).map_err(error)?;
+        if !self.excluded_ports.is_empty() {
+            writeln!(writer, "\nExcluded ports:\n  {}", self.excluded_ports.iter().map(u16::to_string).collect::<Vec<_>>().join(",")).map_err(error)?;
+        }
+        writeln!(writer, "\nTargets:").map_err(error)?;
+        let blocks = self.blocks();
+        let limit = target_limit.unwrap_or(usize::MAX);
+        for block in blocks.iter().take(limit) {
+            writeln!(writer, "  {}", block.label(self)).map_err(error)?;
+            for ip in block.annotated.iter().filter(|_| block.first != block.last) {
+                writeln!(writer, "    {}", self.target_label(*ip)).map_err(error)?;
+            }
+        }
+        if blocks.len() > limit {
+            writeln!(writer, "  ... and {} more ranges (save the plan with -f for the full list)", blocks.len() - limit).map_err(error)?;
+        }
+        if !self.extra_pairs.is_empty() {
+            writeln!(writer, "\nHost:port pairs:").map_err(error)?;
+            for (ip, port) in &self.extra_pairs {
+                writeln!(writer, "  {}", self.socket_label(*ip, *port)).map_err(error)?;
+            }
+        }
+        writer.flush().map_err(error)
+    }
+
+    /// Write the plan as one JSON document, targets as merged ranges
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<(), String> {
+        serde_json::to_writer_pretty(writer, self).map_err(|e| format!("Failed to write the scan plan: {}", e))
+    }
+
+    /// The target ranges, each with its annotated addresses
+    fn blocks(&self) -> Vec<TargetBlock> {
+        let mut annotated = self.targets.annotated_addresses().into_iter().peekable();
+        self.targets.ranges()
+            .map(|(first, last)| {
+                let mut block = TargetBlock { first, last, annotated: Vec::new() };
+                while let Some(ip) = annotated.next_if(|ip| *ip <= last) {
+                    block.annotated.push(ip);
+                }
+                block
+            })
+            .collect()
+    }
+
+    /// An address with its zone, hostnames and tags, as listed in the text plan
+    fn target_label(&self, ip: IpAddr) -> String {
+        let target = self.target(ip);
+        let mut label = target.address;
+        if !target.hostnames.is_empty() {
+            label.push_str(&format!(" ({})", target.hostnames.join(", ")));
+        }
+        if let Some(tags) = target.tags {
+            let tags: Vec<String> = tags.iter().map(|(key, value)| format!("{}={}", key, value)).collect();
+            label.push_str(&format!(" {{{}}}", tags.join(", ")));
+        }
+        label
+    }
+
+    fn target(&self, ip: IpAddr) -> PlannedTarget<'_> {
+        PlannedTarget {
+            address: match self.targets.zone(&ip) {
+                Some(zone) => format!("{}%{}", ip, zone),
+                None => ip.to_string(),
+            },
+            hostnames: self.targets.hostnames(&ip),
+            tags: self.targets.tags(&ip),
+        }
+    }
+
+    fn socket_label(&self, ip: IpAddr, port: u16) -> String {
+        match self.targets.zone(&ip) {
+            Some(zone) => format!("[{}%{}]:{}", ip, zone, port),
+            None => SocketAddr::new(ip, port).to_string(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct PlannedTarget<'a> {
+    address: String,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    hostnames: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<&'a BTreeMap<String, String>>,
+}
+
+/// Inclusive range of target addresses, with those of them that carry annotations
+struct TargetBlock {
+    first: IpAddr,
+    last: IpAddr,
+    annotated: Vec<IpAddr>,
+}
+
+impl TargetBlock {
+    fn count(&self) -> u128 {
+        let value = |ip: IpAddr| match ip {
+            IpAddr::V4(ip) => u32::from(ip) as u128,
+            IpAddr::V6(ip) => u128::from(ip),
+        };
+        (value(self.last) - value(self.first)).saturating_add(1)
+    }
+
+    /// A single address with its annotations, or `first-last (N addresses)`
+    fn label(&self, plan: &ScanPlan) -> String {
+        if self.first == self.last {
+            return plan.target_label(self.first);
+        }
+        format!("{}-{} ({} addresses)", self.first, self.last, self.count())
+    }
+}
+
+#[derive(Serialize)]
+struct PlannedBlock<'a> {
+    first: IpAddr,
+    last: IpAddr,
+    count: u128,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    annotated: Vec<PlannedTarget<'a>>,
+}
+
+impl Serialize for ScanPlan {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let pairs: Vec<String> = self.extra_pairs.iter().map(|&(ip, port)| self.socket_label(ip, port)).collect();
+        let mut plan = serializer.serialize_struct("ScanPlan", 19)?;
+        plan.serialize_field("hosts", &self.hosts)?;
+        plan.serialize_field("out_of_scope", &self.out_of_scope)?;
+        plan.serialize_field("public_hosts", &self.public_hosts)?;
+        plan.serialize_field("ports", &self.ports.len())?;
+        plan.serialize_field("extra_pairs", &self.extra_pairs.len())?;
+        plan.serialize_field("total_probes", &self.probes())?;
+        plan.serialize_field("skipped", &self.skipped)?;
+        let special: Vec<SpecialAddresses> = self.special_addresses.iter()
+            .map(|(class, count)| SpecialAddresses { class: class.name(), count: *count, action: self.address_policy.action(*class).name() })
+            .collect();
+        plan.serialize_field("special_addresses", &special)?;
+        plan.serialize_field("concurrency", &self.concurrency)?;
+        plan.serialize_field("initial_window", &self.initial_window)?;
+        plan.serialize_field("connect_timeout_ms", &self.connect_timeout.as_millis())?;
+        plan.serialize_field("max_retries", &self.max_retries)?;
+        plan.serialize_field("max_rate", &self.max_rate)?;
+        plan.serialize_field("seed", &self.seed)?;
+        plan.serialize_field("estimate_seconds", &EstimateRange {
+            fastest: self.fastest().as_secs_f64(),
+            slowest: self.slowest().as_secs_f64(),
+        })?;
+        plan.serialize_field("port_list", &self.ports)?;
+        plan.serialize_field("excluded_ports", &self.excluded_ports)?;
+        plan.serialize_field("pairs", &pairs)?;
+        let blocks: Vec<PlannedBlock> = self.blocks().into_iter()
+            .map(|block| PlannedBlock {
+                first: block.first,
+                last: block.last,
+                count: block.count(),
+                annotated: block.annotated.iter().map(|ip| self.target(*ip)).collect(),
+            })
+            .collect();
+        plan.serialize_field("targets", &blocks)?;
+        plan.end()
+    }
+}
+
+#[derive(Serialize)]
+struct SpecialAddresses {
+    class: &'static str,
+    count: u128,
+    action: &'static str,
+}
+
+#[derive(Serialize)]
+struct EstimateRange {
+    fastest: f64,
+    slowest: f64,
+}
+
+/// Duration as days, hours, minutes and seconds, e.g. `2h 05m 10s` or `0.8s`
+pub fn format_duration(duration: Duration) -> String {
+    if duration == Duration::MAX {
+        return "more than a lifetime".to_string();
+    }
+    let seconds = duration.as_secs();
+    if seconds < 60 {
+        return format!("{:.1}s", duration.as_secs_f64());
+    }
+    let (days, hours, minutes, seconds) = (seconds / 86_400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m {:02}s", minutes, seconds),
+        (0, _) => format!("{}h {:02}m {:02}s", hours, minutes, seconds),
+        _ => format!("{}d {:02}h {:02}m", days, hours, minutes),
+    }
+}
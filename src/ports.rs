@@ -0,0 +1,244 @@
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Bundled service table in nmap-services layout: name, port/protocol, frequency
+const SERVICES: &str = include_str!("../data/ruscan-services");
+
+/// Short names accepted in port specifications on top of the service table
+pub const SERVICE_NAMES: [(&str, u16); 32] = [
+    ("ftp-data", 20),
+    ("ftp", 21),
+    ("ssh", 22),
+    ("telnet", 23),
+    ("smtp", 25),
+    ("domain", 53),
+    ("dns", 53),
+    ("tftp", 69),
+    ("http", 80),
+    ("kerberos", 88),
+    ("pop3", 110),
+    ("rpcbind", 111),
+    ("ntp", 123),
+    ("netbios-ssn", 139),
+    ("imap", 143),
+    ("snmp", 161),
+    ("ldap", 389),
+    ("https", 443),
+    ("microsoft-ds", 445),
+    ("smb", 445),
+    ("submission", 587),
+    ("ldaps", 636),
+    ("imaps", 993),
+    ("pop3s", 995),
+    ("mssql", 1433),
+    ("mysql", 3306),
+    ("rdp", 3389),
+    ("postgresql", 5432),
+    ("vnc", 5900),
+    ("redis", 6379),
+    ("http-alt", 8080),
+    ("https-alt", 8443),
+];
+
+/// Transport protocol a port specification applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Ports selected by one or more port specifications, deduplicated and sorted
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortSpec {
+    tcp: BTreeSet<u16>,
+    udp: BTreeSet<u16>,
+}
+
+impl PortSpec {
+    pub fn tcp(&self) -> impl Iterator<Item = u16> + '_ {
+        self.tcp.iter().copied()
+    }
+
+    pub fn udp(&self) -> impl Iterator<Item = u16> + '_ {
+        self.udp.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tcp.is_empty() && self.udp.is_empty()
+    }
+
+    /// Add every port of `other`; ports given twice are kept once
+    pub fn merge(&mut self, other: PortSpec) {
+        self.tcp.extend(other.tcp);
+        self.udp.extend(other.udp);
+    }
+
+    fn insert(&mut self, protocol: Protocol, ports: impl IntoIterator<Item = u16>) {
+        match protocol {
+            Protocol::Tcp => self.tcp.extend(ports),
+            Protocol::Udp => self.udp.extend(ports),
+        }
+    }
+}
+
+/// Why a port specification was rejected; each variant carries the offending token
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortSpecError {
+    /// Nothing but separators
+    Empty,
+    /// Not a number between 0 and 65535
+    InvalidPort(String),
+    /// A `start-end` range whose start is greater than its end
+    ReversedRange(String),
+    /// A name neither in [`SERVICE_NAMES`] nor in the service table
+    UnknownService(String),
+    /// A prefix other than `T:` or `U:`
+    UnknownProtocol(String),
+}
+
+impl fmt::Display for PortSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PortSpecError::Empty => write!(f, "Empty port specification"),
+            PortSpecError::InvalidPort(token) => write!(f, "Invalid port \"{}\": expected a number between 0 and 65535", token),
+            PortSpecError::ReversedRange(token) => write!(f, "Invalid port range \"{}\": start is greater than end", token),
+            PortSpecError::UnknownService(token) => write!(f, "Unknown service name \"{}\"", token),
+            PortSpecError::UnknownProtocol(token) => write!(f, "Unknown protocol prefix \"{}\" (use T: or U:)", token),
+        }
+    }
+}
+
+impl std::error::Error for PortSpecError {}
+
+/// Parse a port specification such as `22,80,8000-8100`, `-` (every port 1-65535),
+/// `http,ssh` or `T:80,443,U:53`.
+///
+/// Items are separated by commas. A range may leave out its start (from 1) or its
+/// end (up to 65535). A `T:` or `U:` prefix selects the protocol for the item it is
+/// attached to and every following one; TCP is the default.
+pub fn parse_port_spec(spec: &str) -> Result<PortSpec, PortSpecError> {
+    let mut ports = PortSpec::default();
+    let mut protocol = Protocol::Tcp;
+    for item in spec.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+        let item = match item.split_once(':') {
+            Some((prefix, rest)) => {
+                protocol = match prefix.trim().to_ascii_uppercase().as_str() {
+                    "T" => Protocol::Tcp,
+                    "U" => Protocol::Udp,
+                    _ => return Err(PortSpecError::UnknownProtocol(prefix.trim().to_string())),
+                };
+                rest.trim()
+            }
+            None => item,
+        };
+        if item.is_empty() {
+            continue;
+        }
+        if item.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            let port = service_port(item).ok_or_else(|| PortSpecError::UnknownService(item.to_string()))?;
+            ports.insert(protocol, [port]);
+            continue;
+        }
+        match item.split_once('-') {
+            Some((start, end)) => {
+                let start = if start.trim().is_empty() { 1 } else { parse_port(start)? };
+                let end = if end.trim().is_empty() { 65535 } else { parse_port(end)? };
+                if start > end {
+                    return Err(PortSpecError::ReversedRange(item.to_string()));
+                }
+                ports.insert(protocol, start..=end);
+            }
+            None => ports.insert(protocol, [parse_port(item)?]),
+        }
+    }
+    if ports.is_empty() {
+        return Err(PortSpecError::Empty);
+    }
+    Ok(ports)
+}
+
+/// One entry of the bundled service table
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ServiceEntry {
+    pub name: &'static str,
+    pub port: u16,
+    pub protocol: Protocol,
+    /// Relative likelihood of finding the port open; only the ranking is meaningful
+    pub frequency: f64,
+}
+
+struct ServiceTable {
+    /// Sorted by descending frequency, then ascending port
+    entries: Vec<ServiceEntry>,
+    names: HashMap<(u16, Protocol), &'static str>,
+}
+
+fn service_table() -> &'static ServiceTable {
+    static TABLE: OnceLock<ServiceTable> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut entries: Vec<ServiceEntry> = SERVICES.lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let name = fields.next()?;
+                let (port, protocol) = fields.next()?.split_once('/')?;
+                let protocol = match protocol {
+                    "tcp" => Protocol::Tcp,
+                    "udp" => Protocol::Udp,
+                    _ => return None,
+                };
+                Some(ServiceEntry {
+                    name,
+                    port: port.parse().ok()?,
+                    protocol,
+                    frequency: fields.next()?.parse().ok()?,
+                })
+            })
+            .collect();
+        entries.sort_by(|a, b| b.frequency.total_cmp(&a.frequency).then(a.port.cmp(&b.port)));
+        let names = entries.iter()
+            .filter(|entry| entry.name != "unknown")
+            .map(|entry| ((entry.port, entry.protocol), entry.name))
+            .collect();
+        ServiceTable { entries, names }
+    })
+}
+
+/// Every entry of the bundled service table, most frequently open first
+pub fn services() -> &'static [ServiceEntry] {
+    &service_table().entries
+}
+
+/// Registered service name of a port, e.g. `http` for 80/tcp
+pub fn service_name(port: u16, protocol: Protocol) -> Option<&'static str> {
+    service_table().names.get(&(port, protocol)).copied()
+}
+
+/// The `n` ports most frequently found open, most frequent first; fewer when the
+/// table ranks fewer ports for `protocol`
+pub fn top_ports(n: usize, protocol: Protocol) -> Vec<u16> {
+    services().iter()
+        .filter(|entry| entry.protocol == protocol && entry.frequency > 0.0)
+        .take(n)
+        .map(|entry| entry.port)
+        .collect()
+}
+
+/// TCP port of a service name, case-insensitively: a short name from
+/// [`SERVICE_NAMES`], or the most frequent table entry of that name
+pub fn service_port(name: &str) -> Option<u16> {
+    let name = name.trim();
+    SERVICE_NAMES.iter()
+        .find(|(service, _)| service.eq_ignore_ascii_case(name))
+        .map(|&(_, port)| port)
+        .or_else(|| {
+            services().iter()
+                .find(|entry| entry.protocol == Protocol::Tcp && entry.name.eq_ignore_ascii_case(name))
+                .map(|entry| entry.port)
+        })
+}
+
+fn parse_port(port: &str) -> Result<u16, PortSpecError> {
+    port.trim().parse().map_err(|_| PortSpecError::InvalidPort(port.trim().to_string()))
+}
@@ -0,0 +1,68 @@
+use crate::timing::TimingTemplate;
+use std::str::FromStr;
+
+/// Ports scanned when none are given on the command line
+pub const COMMON_PORTS: [u16; 15] = [21, 22, 23, 25, 53, 80, 110, 143, 443, 993, 995, 3306, 3389, 5432, 8080];
+
+/// Ports commonly serving HTTP(S) front-ends, admin consoles and dev servers
+pub const WEB_PORTS: [u16; 20] = [
+    80, 81, 443, 591, 2082, 2083, 3000, 4443, 5000, 7001, 8000, 8008, 8080, 8081, 8443, 8800, 8888, 9000, 9090, 9443,
+];
+
+/// Named bundle of ports, service detection and timing for a kind of engagement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanProfile {
+    /// Common ports at aggressive timing for fast triage
+    Quick,
+    /// Every TCP port with service detection
+    Full,
+    /// Web ports with service detection
+    Web,
+    /// Common ports, one at a time with long delays, no banner grabbing
+    Stealthy,
+}
+
+impl ScanProfile {
+    pub fn ports(&self) -> Vec<u16> {
+        match self {
+            ScanProfile::Quick | ScanProfile::Stealthy => COMMON_PORTS.to_vec(),
+            ScanProfile::Full => (1..=65535).collect(),
+            ScanProfile::Web => WEB_PORTS.to_vec(),
+        }
+    }
+
+    pub fn service_detection(&self) -> bool {
+        matches!(self, ScanProfile::Full | ScanProfile::Web)
+    }
+
+    pub fn timing(&self) -> TimingTemplate {
+        match self {
+            ScanProfile::Quick | ScanProfile::Full => TimingTemplate::Aggressive,
+            ScanProfile::Web => TimingTemplate::Normal,
+            ScanProfile::Stealthy => TimingTemplate::Sneaky,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScanProfile::Quick => "quick",
+            ScanProfile::Full => "full",
+            ScanProfile::Web => "web",
+            ScanProfile::Stealthy => "stealthy",
+        }
+    }
+}
+
+impl FromStr for ScanProfile {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "quick" => Ok(ScanProfile::Quick),
+            "full" => Ok(ScanProfile::Full),
+            "web" => Ok(ScanProfile::Web),
+            "stealthy" => Ok(ScanProfile::Stealthy),
+            _ => Err(format!("Unknown scan profile: {} (use quick, full, web or stealthy)", value)),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Token bucket pacing every connection attempt of a scan, port probes and service
+/// detection probes alike, to a global packets-per-second ceiling.
+///
+/// The bucket holds up to 50ms worth of tokens so short scheduling hiccups do not
+/// lose throughput, without letting a large burst through. An unlimited limiter
+/// still counts attempts so the achieved rate can be reported.
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    burst: Duration,
+    next_slot: Mutex<Option<Instant>>,
+    sent: AtomicU64,
+}
+
+/// Slowest rate accepted: one attempt per hour
+pub const MIN_RATE: f64 = 1.0 / 3600.0;
+
+impl RateLimiter {
+    /// Limit attempts to `max_rate` per second, no slower than [`MIN_RATE`]
+    pub fn new(max_rate: f64) -> Self {
+        let interval = Duration::from_secs_f64(1.0 / max_rate.max(MIN_RATE));
+        let burst = Duration::from_millis(50).max(interval);
+        RateLimiter {
+            interval: Some(interval),
+            burst: burst - interval,
+            next_slot: Mutex::new(None),
+            sent: AtomicU64::new(0),
+        }
+    }
+
+    /// Count attempts without pacing them
+    pub fn unlimited() -> Self {
+        RateLimiter {
+            interval: None,
+            burst: Duration::ZERO,
+            next_slot: Mutex::new(None),
+            sent: AtomicU64::new(0),
+        }
+    }
+
+    /// Wait for a token, then count one attempt
+    pub async fn acquire(&self) {
+        if let Some(interval) = self.interval {
+            let slot = {
+                let mut next_slot = self.next_slot.lock().unwrap();
+                let now = Instant::now();
+                let earliest = now.checked_sub(self.burst).unwrap_or(now);
+                let slot = next_slot.map_or(earliest, |next| next.max(earliest));
+                *next_slot = Some(slot.checked_add(interval).unwrap_or(slot));
+                slot
+            };
+            if slot > Instant::now() {
+                tokio::time::sleep_until(slot.into()).await;
+            }
+        }
+        self.sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Number of attempts let through so far
+    pub fn sent(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+}
+
+/// Parse a rate in attempts per second: a finite number of at least [`MIN_RATE`]
+pub fn parse_rate(value: &str) -> Result<f64, String> {
+    let rate: f64 = value.trim().parse()
+        .map_err(|_| format!("Invalid rate: {}", value))?;
+    if !rate.is_finite() || rate < MIN_RATE {
+        return Err(format!("Rate must be at least one attempt per hour ({:.6}/s): {}", MIN_RATE, value));
+    }
+    Ok(rate)
+}
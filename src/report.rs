@@ -1,20 +1,163 @@
+use serde::de::{self, Deserializer};
+use serde::ser::{SerializeStruct, Serializer};
 use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs::File;
-use std::io::Write;
+use std::io::{self, ErrorKind, Write};
 
+/// State of a port, derived from how the connection attempt ended
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortState {
+    /// The handshake completed
+    Open,
+    /// The host answered with a reset (connection refused)
+    Closed,
+    /// Nothing answered before the timeout, typically a firewall dropping packets
+    Filtered,
+    /// The host or network was reported unreachable (ICMP unreachable)
+    Unreachable,
+    /// The attempt failed locally for another reason
+    Error(String),
+    /// The scan was interrupted before this pair was tried
+    NotAttempted,
+    /// Excluded from the scan; never connected to
+    Skipped,
+}
+
+impl PortState {
+    /// Classify a failed connection attempt
+    pub fn from_io_error(err: &io::Error) -> Self {
+        match err.kind() {
+            ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset => PortState::Closed,
+            ErrorKind::TimedOut => PortState::Filtered,
+            ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable => PortState::Unreachable,
+            _ => PortState::Error(err.to_string()),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        *self == PortState::Open
+    }
+
+    /// Short lowercase label without the error reason
+    pub fn label(&self) -> &'static str {
+        match self {
+            PortState::Open => "open",
+            PortState::Closed => "closed",
+            PortState::Filtered => "filtered",
+            PortState::Unreachable => "unreachable",
+            PortState::Error(_) => "error",
+            PortState::NotAttempted => "not-attempted",
+            PortState::Skipped => "skipped",
+        }
+    }
+}
+
+/// Labels of the states, as serialized in `status`
+const STATE_LABELS: &[&str] = &["open", "closed", "filtered", "unreachable", "error", "not-attempted", "skipped"];
+
+/// Serialized as a `status` label, with the message of an error in `reason`
+impl Serialize for PortState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let reason = match self {
+            PortState::Error(reason) => Some(reason),
+            _ => None,
+        };
+        let mut state = serializer.serialize_struct("PortState", 1 + reason.is_some() as usize)?;
+        state.serialize_field("status", self.label())?;
+        if let Some(reason) = reason {
+            state.serialize_field("reason", reason)?;
+        }
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+struct StateFields {
+    status: String,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for PortState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = StateFields::deserialize(deserializer)?;
+        match fields.status.as_str() {
+            "open" => Ok(PortState::Open),
+            "closed" => Ok(PortState::Closed),
+            "filtered" => Ok(PortState::Filtered),
+            "unreachable" => Ok(PortState::Unreachable),
+            "error" => Ok(PortState::Error(fields.reason.unwrap_or_default())),
+            "not-attempted" => Ok(PortState::NotAttempted),
+            "skipped" => Ok(PortState::Skipped),
+            other => Err(de::Error::unknown_variant(other, STATE_LABELS)),
+        }
+    }
+}
+
+impl fmt::Display for PortState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PortState::Error(reason) => write!(f, "error ({})", reason),
+            state => f.write_str(state.label()),
+        }
+    }
+}
+
+/// Outcome of probing a single host/port pair
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanResult {
     pub host: String,
+    /// Hostnames that resolved to `host`, e.g. from `-d` or a target list
+    #[serde(default)]
+    pub hostnames: Vec<String>,
+    /// Asset tags of `host` from an imported inventory, e.g. `instance_id` or `group`
+    #[serde(default)]
+    pub tags: BTreeMap<String, String>,
     pub port: u16,
-    pub status: String,
+    /// Written as `status`, plus `reason` for an error
+    #[serde(flatten)]
+    pub status: PortState,
+    /// Service registered for the port in the bundled service table, e.g. `http`
+    #[serde(default)]
+    pub registered_service: Option<String>,
+    /// Service identified by banner grabbing
     pub service: Option<String>,
     pub version: Option<String>,
+    /// Extra attempts made after the first one timed out
+    #[serde(default)]
+    pub retries: u32,
 }
 
+impl ScanResult {
+    /// Tags as `key=value` items joined by `separator`
+    pub fn tag_list(&self, separator: &str) -> String {
+        self.tags.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(separator)
+    }
+}
+
+/// Top-level JSON document, flagging reports of interrupted scans
+#[derive(Serialize)]
+struct JsonReport<'a> {
+    complete: bool,
+    results: &'a [ScanResult],
+}
+
+/// Collects scan results and renders them as json, csv, html or text
 pub struct ReportGenerator {
     results: Vec<ScanResult>,
 }
 
+impl Default for ReportGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl ReportGenerator {
     pub fn new() -> Self {
         ReportGenerator {
@@ -26,6 +169,44 @@ impl ReportGenerator {
         self.results.push(result);
     }
 
+    /// Results collected so far, in the order they were added
+    pub fn results(&self) -> &[ScanResult] {
+        &self.results
+    }
+
+    /// Number of pairs an interrupted scan never tried
+    pub fn not_attempted(&self) -> usize {
+        self.results.iter().filter(|r| r.status == PortState::NotAttempted).count()
+    }
+
+    /// Number of pairs left out by exclusions
+    pub fn skipped(&self) -> usize {
+        self.results.iter().filter(|r| r.status == PortState::Skipped).count()
+    }
+
+    /// False when the scan was interrupted before covering every pair
+    pub fn is_complete(&self) -> bool {
+        self.not_attempted() == 0
+    }
+
+    /// One-line count of results per port state, e.g. "2 open, 13 closed, 0 filtered, ..."
+    pub fn summary(&self) -> String {
+        let count = |label: &str| self.results.iter().filter(|r| r.status.label() == label).count();
+        let mut summary = ["open", "closed", "filtered", "unreachable", "error"]
+            .iter()
+            .map(|label| format!("{} {}", count(label), label))
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !self.is_complete() {
+            summary.push_str(&format!(", {} not attempted", self.not_attempted()));
+        }
+        if self.skipped() > 0 {
+            summary.push_str(&format!(", {} skipped", self.skipped()));
+        }
+        summary
+    }
+
+    /// Write the report in `format` to `file_path`, or to stdout when no path is given
     pub fn generate(&self, format: &str, file_path: Option<&str>) -> Result<(), String> {
         match format.to_lowercase().as_str() {
             "json" => self.generate_json(file_path),
@@ -37,7 +218,11 @@ impl ReportGenerator {
     }
 
     fn generate_json(&self, file_path: Option<&str>) -> Result<(), String> {
-        let json = serde_json::to_string_pretty(&self.results)
+        let report = JsonReport {
+            complete: self.is_complete(),
+            results: &self.results,
+        };
+        let json = serde_json::to_string_pretty(&report)
             .map_err(|e| format!("Failed to serialize JSON: {}", e))?;
         
         if let Some(path) = file_path {
@@ -57,16 +242,20 @@ impl ReportGenerator {
             let mut wtr = csv::Writer::from_path(path)
                 .map_err(|e| format!("Failed to create CSV file {}: {}", path, e))?;
 
-            wtr.write_record(&["Host", "Port", "Status", "Service", "Version"])
+            wtr.write_record(["Host", "Hostnames", "Tags", "Port", "Status", "Registered Service", "Service", "Version", "Retries"])
                 .map_err(|e| format!("Failed to write CSV header: {}", e))?;
 
             for result in &self.results {
-                wtr.write_record(&[
+                wtr.write_record([
                     &result.host,
+                    &result.hostnames.join(" "),
+                    &result.tag_list(";"),
                     &result.port.to_string(),
-                    &result.status,
+                    &result.status.to_string(),
+                    result.registered_service.as_deref().unwrap_or(""),
                     result.service.as_deref().unwrap_or(""),
                     result.version.as_deref().unwrap_or(""),
+                    &result.retries.to_string(),
                 ]).map_err(|e| format!("Failed to write CSV record: {}", e))?;
             }
 
@@ -75,16 +264,20 @@ impl ReportGenerator {
         } else {
             let mut wtr = csv::Writer::from_writer(std::io::stdout());
 
-            wtr.write_record(&["Host", "Port", "Status", "Service", "Version"])
+            wtr.write_record(["Host", "Hostnames", "Tags", "Port", "Status", "Registered Service", "Service", "Version", "Retries"])
                 .map_err(|e| format!("Failed to write CSV header: {}", e))?;
 
             for result in &self.results {
-                wtr.write_record(&[
+                wtr.write_record([
                     &result.host,
+                    &result.hostnames.join(" "),
+                    &result.tag_list(";"),
                     &result.port.to_string(),
-                    &result.status,
+                    &result.status.to_string(),
+                    result.registered_service.as_deref().unwrap_or(""),
                     result.service.as_deref().unwrap_or(""),
                     result.version.as_deref().unwrap_or(""),
+                    &result.retries.to_string(),
                 ]).map_err(|e| format!("Failed to write CSV record: {}", e))?;
             }
 
@@ -106,37 +299,62 @@ impl ReportGenerator {
         tr:nth-child(even) { background-color: #f2f2f2; }
         .open { color: green; font-weight: bold; }
         .closed { color: red; }
+        .filtered { color: darkorange; }
+        .unreachable { color: gray; }
+        .error { color: purple; }
+        .not-attempted { color: gray; font-style: italic; }
+        .skipped { color: gray; text-decoration: line-through; }
+        .incomplete { color: darkorange; font-weight: bold; }
     </style>
 </head>
 <body>
     <h1>Ruscan Scan Results</h1>
-    <table>
+"#);
+        if !self.is_complete() {
+            html.push_str(&format!(
+                "    <p class=\"incomplete\">Incomplete scan: {} host/port pairs were not attempted</p>\n",
+                self.not_attempted()
+            ));
+        }
+        html.push_str(r#"    <table>
         <tr>
             <th>Host</th>
+            <th>Hostnames</th>
+            <th>Tags</th>
             <th>Port</th>
             <th>Status</th>
+            <th>Registered Service</th>
             <th>Service</th>
             <th>Version</th>
+            <th>Retries</th>
         </tr>
 "#);
 
         for result in &self.results {
-            let status_class = if result.status == "open" { "open" } else { "closed" };
+            let status_class = result.status.label();
             html.push_str(&format!(
                 r#"        <tr>
             <td>{}</td>
             <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
             <td class="{}">{}</td>
             <td>{}</td>
             <td>{}</td>
+            <td>{}</td>
+            <td>{}</td>
         </tr>
 "#,
-                result.host,
+                escape_html(&result.host),
+                escape_html(&result.hostnames.join(", ")),
+                escape_html(&result.tag_list(", ")),
                 result.port,
                 status_class,
-                result.status,
-                result.service.as_deref().unwrap_or("N/A"),
-                result.version.as_deref().unwrap_or("N/A"),
+                escape_html(&result.status.to_string()),
+                escape_html(result.registered_service.as_deref().unwrap_or("N/A")),
+                escape_html(result.service.as_deref().unwrap_or("N/A")),
+                escape_html(result.version.as_deref().unwrap_or("N/A")),
+                result.retries,
             ));
         }
 
@@ -158,10 +376,14 @@ impl ReportGenerator {
 
     fn generate_text(&self, file_path: Option<&str>) -> Result<(), String> {
         let mut output = String::new();
+        if !self.is_complete() {
+            output.push_str(&format!("# Incomplete scan: {} host/port pairs not attempted\n", self.not_attempted()));
+        }
         
         for result in &self.results {
-            // Only include open ports in text output
-            if result.status != "open" {
+            // Only include open ports, excluded pairs and pairs an interrupted scan
+            // never tried in text output
+            if !matches!(result.status, PortState::Open | PortState::NotAttempted | PortState::Skipped) {
                 continue;
             }
             
@@ -175,14 +397,34 @@ impl ReportGenerator {
                 String::new()
             };
             
+            let registered = result.registered_service.as_deref()
+                .map(|name| format!(" [{}]", name))
+                .unwrap_or_default();
+
+            let hostnames = if result.hostnames.is_empty() {
+                String::new()
+            } else {
+                format!(" ({})", result.hostnames.join(", "))
+            };
+
+            let tags = if result.tags.is_empty() {
+                String::new()
+            } else {
+                format!(" {{{}}}", result.tag_list(", "))
+            };
+
             output.push_str(&format!(
-                "{}:{} - {} {}\n",
+                "{}:{}{}{} - {}{} {}\n",
                 result.host,
                 result.port,
+                hostnames,
+                tags,
                 result.status,
+                registered,
                 service_info
             ));
         }
+        output.push_str(&format!("# {}\n", self.summary()));
 
         if let Some(path) = file_path {
             let mut file = File::create(path)
@@ -197,3 +439,19 @@ impl ReportGenerator {
     }
 }
 
+/// `text` with the characters HTML gives meaning to replaced by entities, so banners,
+/// hostnames and inventory tags cannot inject markup into a report
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,979 @@
+use crate::checkpoint::{self, Checkpoint, CheckpointWriter};
+use crate::congestion::{Adjustment, CongestionController};
+use crate::network::{AddressClass, AddressPolicy, TargetSet};
+use crate::order::{self, CyclicPermutation};
+use crate::plan::ScanPlan;
+use crate::ports::{self, Protocol};
+use crate::rate::RateLimiter;
+use crate::report::{PortState, ScanResult};
+use crate::scope::Scope;
+use crate::service;
+use crate::timing::{AdaptiveTimeouts, HostPacer, TimingTemplate};
+use chrono::Local;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::net::{IpAddr, SocketAddr, SocketAddrV6};
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tokio::time::timeout;
+
+/// Default maximum number of concurrent connections
+pub const DEFAULT_CONCURRENCY: usize = 1000;
+/// Default timeout for a single connection attempt
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+/// Default timeout for each read made during service detection
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a scan with a scope checks that its time window is still open
+const WINDOW_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Builder for a TCP connect scan over a set of hosts and ports
+#[derive(Debug, Clone)]
+pub struct Scanner {
+    targets: TargetSet,
+    ports: Vec<u16>,
+    explicit_pairs: Vec<(IpAddr, u16)>,
+    exclude: TargetSet,
+    exclude_ports: BTreeSet<u16>,
+    concurrency: usize,
+    service_detection: bool,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    adaptive_timeout: bool,
+    max_retries: u32,
+    scan_delay: Duration,
+    max_scan_delay: Duration,
+    max_per_host: Option<usize>,
+    max_rate: Option<f64>,
+    min_rate: Option<f64>,
+    adaptive_concurrency: bool,
+    randomize: bool,
+    seed: u64,
+    verbose: bool,
+    checkpoint: Option<Arc<Mutex<CheckpointWriter>>>,
+    resume: Option<Arc<Checkpoint>>,
+    scope: Option<Arc<Scope>>,
+    address_policy: AddressPolicy,
+}
+
+impl Default for Scanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scanner {
+    pub fn new() -> Self {
+        Scanner {
+            targets: TargetSet::new(),
+            ports: Vec::new(),
+            explicit_pairs: Vec::new(),
+            exclude: TargetSet::new(),
+            exclude_ports: BTreeSet::new(),
+            concurrency: DEFAULT_CONCURRENCY,
+            service_detection: false,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            adaptive_timeout: false,
+            max_retries: 0,
+            scan_delay: Duration::ZERO,
+            max_scan_delay: Duration::ZERO,
+            max_per_host: None,
+            max_rate: None,
+            min_rate: None,
+            adaptive_concurrency: true,
+            randomize: true,
+            seed: order::random_seed(),
+            verbose: false,
+            checkpoint: None,
+            resume: None,
+            scope: None,
+            address_policy: AddressPolicy::default(),
+        }
+    }
+
+    /// Add a single target address (IPv4 or IPv6)
+    pub fn target(mut self, addr: IpAddr) -> Self {
+        self.targets.add_ip(addr);
+        self
+    }
+
+    /// Add a set of targets, e.g. built from `network::parse_cidr`; addresses are
+    /// expanded lazily while the scan runs
+    pub fn targets(mut self, targets: TargetSet) -> Self {
+        self.targets.extend(targets);
+        self
+    }
+
+    /// Add a single port to scan on every target
+    pub fn port(mut self, port: u16) -> Self {
+        self.ports.push(port);
+        self
+    }
+
+    /// Add several ports to scan on every target
+    pub fn ports<I: IntoIterator<Item = u16>>(mut self, ports: I) -> Self {
+        self.ports.extend(ports);
+        self
+    }
+
+    /// Scan `port` on `addr` only, on top of every target x port combination
+    pub fn pair(mut self, addr: IpAddr, port: u16) -> Self {
+        self.explicit_pairs.push((addr, port));
+        self
+    }
+
+    /// Scan several individual host/port pairs, e.g. `host:port` entries of a target list
+    pub fn pairs<I: IntoIterator<Item = (IpAddr, u16)>>(mut self, pairs: I) -> Self {
+        self.explicit_pairs.extend(pairs);
+        self
+    }
+
+    /// Never connect to these addresses; their pairs are reported as
+    /// [`PortState::Skipped`]. A CIDR excludes its network and broadcast addresses too.
+    pub fn exclude(mut self, targets: TargetSet) -> Self {
+        self.exclude.extend(targets);
+        self
+    }
+
+    /// Only connect to targets the scope permits, reporting the others as
+    /// [`PortState::Skipped`], and stop once the scope's time windows close
+    pub fn scope(mut self, scope: Scope) -> Self {
+        self.scope = Some(Arc::new(scope));
+        self
+    }
+
+    /// What to do with loopback, link-local, multicast, documentation, reserved and
+    /// network/broadcast targets; skipped ones are reported as [`PortState::Skipped`]
+    pub fn address_policy(mut self, policy: AddressPolicy) -> Self {
+        self.address_policy = policy;
+        self
+    }
+
+    /// Never connect to these ports on any target; their pairs are reported as
+    /// [`PortState::Skipped`]
+    pub fn exclude_ports<I: IntoIterator<Item = u16>>(mut self, ports: I) -> Self {
+        self.exclude_ports.extend(ports);
+        self
+    }
+
+    /// Maximum number of connections in flight at once, between 1 and
+    /// [`Semaphore::MAX_PERMITS`]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.clamp(1, Semaphore::MAX_PERMITS);
+        self
+    }
+
+    /// Run banner grabbing against every open port
+    pub fn service_detection(mut self, enabled: bool) -> Self {
+        self.service_detection = enabled;
+        self
+    }
+
+    /// Timeout for each connection attempt, including those made by service detection.
+    /// With adaptive timeouts this is the starting value and the upper bound.
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = timeout;
+        self
+    }
+
+    /// Timeout for each wait on a service banner or probe response
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Shrink connect timeouts per host from the round-trip times measured so far
+    pub fn adaptive_timeout(mut self, enabled: bool) -> Self {
+        self.adaptive_timeout = enabled;
+        self
+    }
+
+    /// Number of extra attempts for a port whose connection timed out; ports that
+    /// answered with a reset or were reported unreachable are never retried
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Minimum pause between two probes against the same host
+    pub fn scan_delay(mut self, delay: Duration) -> Self {
+        self.scan_delay = delay;
+        self
+    }
+
+    /// Upper bound the per-host delay may grow to when a responsive host starts
+    /// dropping probes; at or below `scan_delay` the delay stays fixed
+    pub fn max_scan_delay(mut self, delay: Duration) -> Self {
+        self.max_scan_delay = delay;
+        self
+    }
+
+    /// Maximum number of connections in flight against any single host
+    pub fn max_per_host(mut self, limit: usize) -> Self {
+        self.max_per_host = Some(limit.max(1));
+        self
+    }
+
+    /// Ceiling on connection attempts per second across the whole scan, service
+    /// detection included
+    pub fn max_rate(mut self, rate: f64) -> Self {
+        self.max_rate = Some(rate).filter(|r| *r > 0.0);
+        self
+    }
+
+    /// Floor on connection attempts per second: when every slot is busy for longer
+    /// than `1 / rate`, concurrency is raised instead of waiting
+    pub fn min_rate(mut self, rate: f64) -> Self {
+        self.min_rate = Some(rate).filter(|r| *r > 0.0);
+        self
+    }
+
+    /// Grow the number of probes in flight while hosts answer and halve it when
+    /// timeouts pile up (AIMD), with `concurrency` as the ceiling. On by default.
+    pub fn adaptive_concurrency(mut self, enabled: bool) -> Self {
+        self.adaptive_concurrency = enabled;
+        self
+    }
+
+    /// Visit the host/port pairs in a random order (the default) rather than
+    /// ascending port by port
+    pub fn randomize(mut self, enabled: bool) -> Self {
+        self.randomize = enabled;
+        self
+    }
+
+    /// Seed of the random scan order; the same seed and specification reproduce
+    /// the same order. A fresh seed is picked by [`Scanner::new`].
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Seed the scan order is derived from, or `None` when pairs are visited
+    /// sequentially, either by request or because the scan space is too large to
+    /// permute
+    pub fn order_seed(&self) -> Option<u64> {
+        (self.randomize && self.total_probes() <= order::MAX_PERMUTED).then_some(self.seed)
+    }
+
+    /// Report engine adjustments (concurrency window, per-host delays) on stderr
+    pub fn verbose(mut self, enabled: bool) -> Self {
+        self.verbose = enabled;
+        self
+    }
+
+    /// Journal every finished pair to a state file so the scan can be resumed
+    pub fn checkpoint(mut self, writer: CheckpointWriter) -> Self {
+        self.checkpoint = Some(Arc::new(Mutex::new(writer)));
+        self
+    }
+
+    /// Skip the pairs an earlier run already finished. The caller is expected to have
+    /// checked [`Checkpoint::fingerprint`] against [`Scanner::fingerprint`].
+    pub fn resume(mut self, checkpoint: Checkpoint) -> Self {
+        self.resume = Some(Arc::new(checkpoint));
+        self
+    }
+
+    /// Stable identifier of the target and port specification, in scan order
+    pub fn fingerprint(&self) -> String {
+        let targets: Vec<String> = self.targets.specs().iter().map(|spec| spec.to_string()).collect();
+        let ports = self.ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+        let pairs = self.explicit_pairs.iter()
+            .map(|(addr, port)| self.socket_addr(*addr, *port).to_string())
+            .collect::<Vec<_>>().join(",");
+        let order = self.order_seed().map_or("sequential".to_string(), |seed| seed.to_string());
+        let exclude: Vec<String> = self.exclude.specs().iter().map(|spec| spec.to_string()).collect();
+        let exclude_ports = self.exclude_ports.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+        checkpoint::fingerprint(
+            targets.iter().map(|t| t.as_bytes())
+                .chain([b"ports" as &[u8], ports.as_bytes(), b"pairs", pairs.as_bytes(), b"order", order.as_bytes(), b"exclude"])
+                .chain(exclude.iter().map(|e| e.as_bytes()))
+                .chain([b"exclude-ports" as &[u8], exclude_ports.as_bytes()]),
+        )
+    }
+
+    /// Apply every setting of a timing template; later builder calls override it
+    pub fn timing(self, template: TimingTemplate) -> Self {
+        let settings = template.settings();
+        self.concurrency(settings.concurrency)
+            .connect_timeout(settings.connect_timeout)
+            .read_timeout(settings.read_timeout)
+            .max_retries(settings.max_retries)
+            .scan_delay(settings.scan_delay)
+            .adaptive_timeout(settings.adaptive_timeout)
+    }
+
+    /// Number of host/port pairs this scanner will probe
+    pub fn total_probes(&self) -> u128 {
+        self.grid_size().saturating_add(self.extra_pairs().len() as u128)
+    }
+
+    /// Number of host/port pairs probed on top of the target x port grid
+    pub fn extra_pair_count(&self) -> usize {
+        self.extra_pairs().len()
+    }
+
+    /// Counts, settings and time estimate of the scan, without connecting anywhere
+    pub fn plan(&self) -> ScanPlan {
+        let extra_pairs = self.extra_pairs();
+        let out_of_scope = match &self.scope {
+            Some(scope) => scope.out_of_scope(&self.targets),
+            None => TargetSet::new(),
+        };
+        let mut skipped_hosts = self.exclude.clone();
+        skipped_hosts.extend(out_of_scope.clone());
+        let remaining = self.targets.difference(&skipped_hosts);
+        let special_addresses = self.targets.class_counts(&skipped_hosts);
+        let targets = remaining.without_skipped(&self.address_policy);
+        let hosts = targets.len() - special_addresses.iter()
+            .find(|&&(class, _)| class == AddressClass::NetworkBroadcast && self.address_policy.skips(Some(class)))
+            .map_or(0, |&(_, count)| count);
+        let (excluded_ports, ports): (Vec<u16>, Vec<u16>) = self.ports.iter()
+            .partition(|port| self.exclude_ports.contains(port));
+        let probed_pairs = extra_pairs.iter()
+            .filter(|(addr, port)| !self.is_skipped(addr, *port))
+            .count() as u128;
+        let total_pairs = self.total_probes();
+        let probes = hosts.saturating_mul(ports.len() as u128).saturating_add(probed_pairs);
+        ScanPlan {
+            targets,
+            hosts,
+            ports,
+            excluded_ports,
+            total_pairs,
+            skipped: total_pairs.saturating_sub(probes),
+            extra_pairs,
+            concurrency: self.concurrency,
+            initial_window: if self.adaptive_concurrency {
+                CongestionController::new(self.concurrency).window()
+            } else {
+                self.concurrency
+            },
+            connect_timeout: self.connect_timeout,
+            max_retries: self.max_retries,
+            max_rate: self.max_rate,
+            scan_delay: self.scan_delay,
+            max_per_host: self.max_per_host,
+            seed: self.order_seed(),
+            out_of_scope: out_of_scope.len(),
+            public_hosts: remaining.public_count(),
+            special_addresses,
+            address_policy: self.address_policy,
+        }
+    }
+
+    /// Whether the pair is reported as skipped: its port or host is excluded, the
+    /// scope rules it out, or the address policy skips the host's class
+    fn is_skipped(&self, addr: &IpAddr, port: u16) -> bool {
+        self.exclude_ports.contains(&port)
+            || self.exclude.covers(addr)
+            || !self.in_scope(addr)
+            || self.address_policy.skips(self.targets.class_of(addr))
+    }
+
+    /// Whether the scope, if any, permits connecting to `addr`
+    fn in_scope(&self, addr: &IpAddr) -> bool {
+        self.scope.as_ref().is_none_or(|scope| scope.permits(addr, self.targets.hostnames(addr)))
+    }
+
+    /// Number of target x port combinations
+    fn grid_size(&self) -> u128 {
+        self.targets.len().saturating_mul(self.ports.len() as u128)
+    }
+
+    /// Attach the hostnames `addr` was resolved from, its asset tags, and its zone
+    /// ID if it has one
+    fn annotate(&self, result: ScanResult, addr: &IpAddr) -> ScanResult {
+        ScanResult {
+            host: self.host_label(addr),
+            hostnames: self.targets.hostnames(addr).to_vec(),
+            tags: self.targets.tags(addr).cloned().unwrap_or_default(),
+            ..result
+        }
+    }
+
+    /// `addr` as shown in results, e.g. `fe80::1%eth0` for a scoped address
+    fn host_label(&self, addr: &IpAddr) -> String {
+        match self.targets.zone(addr) {
+            Some(zone) => format!("{}%{}", addr, zone),
+            None => addr.to_string(),
+        }
+    }
+
+    /// Address to connect to, carrying the scope ID of a link-local target
+    fn socket_addr(&self, addr: IpAddr, port: u16) -> SocketAddr {
+        match (addr, self.targets.zone(&addr)) {
+            (IpAddr::V6(ip), Some(zone)) => SocketAddr::V6(SocketAddrV6::new(ip, port, 0, zone.id)),
+            _ => SocketAddr::new(addr, port),
+        }
+    }
+
+    /// Pair at `index` of the unshuffled scan space: the target x port grid port by
+    /// port, then the explicit pairs
+    fn pair_at(&self, index: u128, extra_pairs: &[(IpAddr, u16)]) -> Option<(IpAddr, u16)> {
+        let grid = self.grid_size();
+        if index < grid {
+            let hosts = self.targets.len();
+            Some((self.targets.get(index % hosts)?, self.ports[(index / hosts) as usize]))
+        } else {
+            extra_pairs.get((index - grid) as usize).copied()
+        }
+    }
+
+    /// Explicit pairs not already covered by the target x port grid, each once
+    fn extra_pairs(&self) -> Vec<(IpAddr, u16)> {
+        let ports: HashSet<u16> = self.ports.iter().copied().collect();
+        let mut seen = HashSet::new();
+        self.explicit_pairs.iter()
+            .map(|&(addr, port)| (addr.to_canonical(), port))
+            .filter(|&(addr, port)| !(ports.contains(&port) && self.targets.contains(&addr)))
+            .filter(|pair| seen.insert(*pair))
+            .collect()
+    }
+
+    /// Run the scan to completion and return every result
+    pub fn scan(&self) -> Vec<ScanResult> {
+        self.scan_stream().collect()
+    }
+
+    /// Start the scan in the background and return an iterator yielding results
+    /// in the order they complete
+    pub fn scan_stream(&self) -> ScanStream {
+        let (tx, rx) = mpsc::channel(self.concurrency);
+        let rate = Arc::new(match self.max_rate {
+            Some(max_rate) => RateLimiter::new(max_rate),
+            None => RateLimiter::unlimited(),
+        });
+        let stop = StopHandle::default();
+        let scanner = self.clone();
+        let engine_rate = rate.clone();
+        let engine_stop = stop.clone();
+        let handle = thread::spawn(move || {
+            let runtime = tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("failed to start the scanning runtime");
+            runtime.block_on(scanner.run(tx, engine_rate, engine_stop));
+        });
+        ScanStream {
+            results: rx,
+            handle: Some(handle),
+            rate,
+            stop,
+        }
+    }
+
+    async fn run(self, tx: Sender<ScanResult>, rate: Arc<RateLimiter>, stop: StopHandle) {
+        // Every probe holds a permit for its whole lifetime, so at most `capacity`
+        // connections (and tasks) exist at any moment
+        let permits = Arc::new(Semaphore::new(self.concurrency));
+        let mut capacity = self.concurrency;
+        let min_rate_interval = self.min_rate.and_then(|r| Duration::try_from_secs_f64(1.0 / r).ok());
+        let ctx = Arc::new(ProbeContext {
+            service_detection: self.service_detection,
+            connect_timeout: self.connect_timeout,
+            read_timeout: self.read_timeout,
+            adaptive: self.adaptive_timeout.then(|| Mutex::new(AdaptiveTimeouts::new(self.connect_timeout))),
+            max_retries: self.max_retries,
+            pacer: (!self.scan_delay.is_zero() || !self.max_scan_delay.is_zero())
+                .then(|| Mutex::new(HostPacer::new(self.scan_delay, self.max_scan_delay))),
+            max_per_host: self.max_per_host,
+            host_slots: Mutex::new(HashMap::new()),
+            congestion: self.adaptive_concurrency.then(|| Mutex::new(CongestionController::new(self.concurrency))),
+            responsive: Mutex::new(HashSet::new()),
+            in_flight: AtomicUsize::new(0),
+            completed: Notify::new(),
+            verbose: self.verbose,
+            rate,
+            stop: stop.clone(),
+            checkpoint: self.checkpoint.clone(),
+            checkpoint_failed: AtomicBool::new(false),
+        });
+
+        // Either permute the whole host x port space, or walk port by port across
+        // every host so consecutive probes still land on different hosts
+        let extra_pairs = &self.extra_pairs();
+        let scanner = &self;
+        let ordered: Box<dyn Iterator<Item = (IpAddr, u16)> + Send + '_> =
+            match self.order_seed().and_then(|seed| CyclicPermutation::new(self.total_probes(), seed)) {
+                Some(permutation) => Box::new(permutation.filter_map(move |index| scanner.pair_at(index, extra_pairs))),
+                None => Box::new(self.ports.iter()
+                    .flat_map(|&port| self.targets.iter().map(move |ip| (ip, port)))
+                    .chain(extra_pairs.iter().copied())),
+            };
+        let mut pairs = ordered
+            .enumerate()
+            .map(|(index, (addr, port))| (index as u64, addr, port))
+            .filter(|(index, _, _)| !self.resume.as_ref().is_some_and(|done| done.is_done(*index)));
+        let mut window_checked = Instant::now() - WINDOW_CHECK_INTERVAL;
+        for (index, addr, port) in pairs.by_ref() {
+            if let Some(scope) = &self.scope {
+                // Stop like an interrupt when the window closes, so the scan can be resumed
+                if window_checked.elapsed() >= WINDOW_CHECK_INTERVAL {
+                    window_checked = Instant::now();
+                    if !scope.window_open(&Local::now()) && !stop.is_stopped() {
+                        ctx.log("Scan window closed, stopping");
+                        stop.stop();
+                    }
+                }
+            }
+            if self.is_skipped(&addr, port) {
+                let result = self.annotate(skipped(addr, port), &addr);
+                ctx.record_progress(index, &result);
+                if tx.send(result).await.is_err() {
+                    // Nobody is listening any more, e.g. the stream was dropped
+                    stop.stop();
+                    break;
+                }
+                continue;
+            }
+            let acquire = async {
+                let acquire = permits.clone().acquire_owned();
+                match min_rate_interval {
+                    Some(interval) => match timeout(interval, acquire).await {
+                        Ok(permit) => permit,
+                        Err(_) if capacity < Semaphore::MAX_PERMITS => {
+                            // Every slot stayed busy too long to keep up --min-rate
+                            permits.add_permits(1);
+                            capacity += 1;
+                            ctx.raise_window_floor();
+                            permits.clone().acquire_owned().await
+                        }
+                        Err(_) => permits.clone().acquire_owned().await,
+                    },
+                    None => acquire.await,
+                }
+            };
+            let permit = match ctx.unless_stopped(acquire).await {
+                Some(Ok(permit)) => permit,
+                _ => {
+                    let _ = tx.send(self.annotate(not_attempted(addr, port), &addr)).await;
+                    break;
+                }
+            };
+            if ctx.unless_stopped(ctx.wait_for_window()).await.is_none() {
+                let _ = tx.send(self.annotate(not_attempted(addr, port), &addr)).await;
+                break;
+            }
+            let tx = tx.clone();
+            let ctx = ctx.clone();
+            let host = self.host_label(&addr);
+            let hostnames = self.targets.hostnames(&addr).to_vec();
+            let tags = self.targets.tags(&addr).cloned().unwrap_or_default();
+            let socket_addr = self.socket_addr(addr, port);
+
+            let in_flight = InFlight::new(ctx.clone());
+            tokio::spawn(async move {
+                let result = ScanResult { host, hostnames, tags, ..ctx.probe(socket_addr).await };
+                ctx.record_progress(index, &result);
+                // The receiver is gone if the caller dropped the stream; stop scheduling
+                if tx.send(result).await.is_err() {
+                    ctx.stop.stop();
+                }
+                drop(permit);
+                drop(in_flight);
+            });
+        }
+
+        // Only non-empty when the scan was stopped early
+        for (_, addr, port) in pairs {
+            if tx.send(self.annotate(not_attempted(addr, port), &addr)).await.is_err() {
+                break;
+            }
+        }
+
+        // Wait for the in-flight probes to finish and hand in their results
+        ctx.drained().await;
+
+        if let Some(writer) = &self.checkpoint {
+            if let Err(e) = writer.lock().unwrap().flush() {
+                eprintln!("[-] Failed to write checkpoint: {}", e);
+            }
+        }
+    }
+}
+
+/// Settings and shared state every probe task needs
+struct ProbeContext {
+    service_detection: bool,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    adaptive: Option<Mutex<AdaptiveTimeouts>>,
+    max_retries: u32,
+    pacer: Option<Mutex<HostPacer>>,
+    max_per_host: Option<usize>,
+    host_slots: Mutex<HashMap<IpAddr, Arc<Semaphore>>>,
+    congestion: Option<Mutex<CongestionController>>,
+    /// Hosts that have answered at least once; only their timeouts count as drops,
+    /// the others' only complete the congestion round
+    responsive: Mutex<HashSet<IpAddr>>,
+    in_flight: AtomicUsize,
+    completed: Notify,
+    verbose: bool,
+    rate: Arc<RateLimiter>,
+    stop: StopHandle,
+    checkpoint: Option<Arc<Mutex<CheckpointWriter>>>,
+    checkpoint_failed: AtomicBool,
+}
+
+impl ProbeContext {
+    fn connect_timeout(&self, host: IpAddr) -> Duration {
+        match &self.adaptive {
+            Some(adaptive) => adaptive.lock().unwrap().connect_timeout(host),
+            None => self.connect_timeout,
+        }
+    }
+
+    /// Run `fut` unless the scan is stopped first, in which case `None` is returned
+    async fn unless_stopped<F: Future>(&self, fut: F) -> Option<F::Output> {
+        tokio::select! {
+            biased;
+            _ = self.stop.stopped() => None,
+            output = fut => Some(output),
+        }
+    }
+
+    /// Journal a finished pair; the first write failure is reported, later ones are not
+    fn record_progress(&self, index: u64, result: &ScanResult) {
+        if let Some(writer) = &self.checkpoint {
+            if let Err(e) = writer.lock().unwrap().record(index, result) {
+                if !self.checkpoint_failed.swap(true, Ordering::SeqCst) {
+                    eprintln!("[-] {}", e);
+                }
+            }
+        }
+    }
+
+    fn log(&self, message: &str) {
+        if self.verbose {
+            eprintln!("[*] {}", message);
+        }
+    }
+
+    fn log_adjustment(&self, adjustment: Option<Adjustment>) {
+        if let Some(adj) = adjustment {
+            self.log(&format!("Concurrency {} -> {} ({:.1}% timeouts)", adj.from, adj.to, adj.drop_ratio * 100.0));
+        }
+    }
+
+    /// Hold the scheduler while the congestion window is full
+    async fn wait_for_window(&self) {
+        let congestion = match &self.congestion {
+            Some(congestion) => congestion,
+            None => return,
+        };
+        loop {
+            // Register for the wakeup before checking, so a completion in between is not missed
+            let completed = self.completed.notified();
+            if self.in_flight.load(Ordering::SeqCst) < congestion.lock().unwrap().window() {
+                return;
+            }
+            completed.await;
+        }
+    }
+
+    /// Wait until no probe is in flight
+    async fn drained(&self) {
+        loop {
+            let completed = self.completed.notified();
+            if self.in_flight.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            completed.await;
+        }
+    }
+
+    /// Let one more probe than are in flight through the congestion window, for the
+    /// permit --min-rate just added
+    fn raise_window_floor(&self) {
+        if let Some(congestion) = &self.congestion {
+            congestion.lock().unwrap().raise_floor(self.in_flight.load(Ordering::SeqCst) + 1);
+        }
+    }
+
+    /// `host` answered after `rtt` (handshake or reset)
+    fn record_answer(&self, host: IpAddr, rtt: Duration) {
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.lock().unwrap().record(host, rtt);
+        }
+        if let Some(pacer) = &self.pacer {
+            pacer.lock().unwrap().record_answer(host);
+        }
+        if let Some(congestion) = &self.congestion {
+            self.responsive.lock().unwrap().insert(host);
+            let adjustment = congestion.lock().unwrap().record_answer();
+            self.log_adjustment(adjustment);
+        }
+    }
+
+    /// `host` reported itself or its network unreachable
+    fn record_unreachable(&self) {
+        if let Some(congestion) = &self.congestion {
+            let adjustment = congestion.lock().unwrap().record_answer();
+            self.log_adjustment(adjustment);
+        }
+    }
+
+    fn record_timeout(&self, host: IpAddr) {
+        if let Some(pacer) = &self.pacer {
+            let grown = pacer.lock().unwrap().record_timeout(host);
+            if let Some(delay) = grown {
+                self.log(&format!("Scan delay for {} raised to {:?} after a dropped probe", host, delay));
+            }
+        }
+        if let Some(congestion) = &self.congestion {
+            let responsive = self.responsive.lock().unwrap().contains(&host);
+            let mut congestion = congestion.lock().unwrap();
+            let adjustment = if responsive { congestion.record_drop() } else { congestion.record_silence() };
+            drop(congestion);
+            self.log_adjustment(adjustment);
+        }
+    }
+
+    /// Wait until `host` may be probed again under --scan-delay
+    async fn pace(&self, host: IpAddr) {
+        if let Some(pacer) = &self.pacer {
+            let slot = pacer.lock().unwrap().reserve(host);
+            tokio::time::sleep_until(slot.into()).await;
+        }
+    }
+
+    /// Take one of `host`'s --max-per-host slots, if that limit is set
+    async fn host_slot(&self, host: IpAddr) -> Option<OwnedSemaphorePermit> {
+        let limit = self.max_per_host?;
+        let semaphore = self.host_slots.lock().unwrap()
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(limit)))
+            .clone();
+        semaphore.acquire_owned().await.ok()
+    }
+
+    /// Give back a --max-per-host slot, forgetting the host once nobody holds or
+    /// waits for its slots so the map only covers hosts currently being probed
+    fn release_host_slot(&self, host: IpAddr, slot: Option<OwnedSemaphorePermit>) {
+        if slot.is_none() {
+            return;
+        }
+        let mut slots = self.host_slots.lock().unwrap();
+        drop(slot);
+        if slots.get(&host).is_some_and(|semaphore| Arc::strong_count(semaphore) == 1) {
+            slots.remove(&host);
+        }
+    }
+
+    async fn probe(&self, socket_addr: SocketAddr) -> ScanResult {
+        let (host, port) = (socket_addr.ip(), socket_addr.port());
+        let slot = match self.unless_stopped(self.host_slot(host)).await {
+            Some(slot) => slot,
+            None => return not_attempted(host, port),
+        };
+        let result = self.probe_with_slot(socket_addr).await;
+        self.release_host_slot(host, slot);
+        result
+    }
+
+    async fn probe_with_slot(&self, socket_addr: SocketAddr) -> ScanResult {
+        let (host, port) = (socket_addr.ip(), socket_addr.port());
+        let mut retries = 0;
+        let (status, service, version) = loop {
+            let paced = self.unless_stopped(async {
+                self.pace(host).await;
+                self.rate.acquire().await;
+            }).await;
+            if paced.is_none() {
+                // Stopped before this attempt: a first attempt never happened, a
+                // retry keeps the timeout already seen
+                if retries == 0 {
+                    return not_attempted(host, port);
+                }
+                break (PortState::Filtered, None, None);
+            }
+            let connect_timeout = self.connect_timeout(host);
+            let started = Instant::now();
+            let status = match timeout(connect_timeout, TcpStream::connect(socket_addr)).await {
+                Ok(Ok(_)) => {
+                    self.record_answer(host, started.elapsed());
+                    if self.service_detection {
+                        let (svc, ver) = service::detect_service_async(&socket_addr, connect_timeout, self.read_timeout, &self.rate).await;
+                        break (PortState::Open, svc, ver);
+                    }
+                    break (PortState::Open, None, None);
+                }
+                Ok(Err(e)) => PortState::from_io_error(&e),
+                // No answer at all before the deadline: something dropped the SYN
+                Err(_) => PortState::Filtered,
+            };
+            match status {
+                // A reset is as good a round-trip measurement as a handshake, and
+                // a definite answer that is not worth asking again
+                PortState::Closed => {
+                    self.record_answer(host, started.elapsed());
+                    break (status, None, None);
+                }
+                // A timeout may just be packet loss, so try again if allowed
+                PortState::Filtered => {
+                    self.record_timeout(host);
+                    if retries >= self.max_retries {
+                        break (status, None, None);
+                    }
+                    retries += 1;
+                }
+                PortState::Unreachable => {
+                    self.record_unreachable();
+                    break (status, None, None);
+                }
+                _ => break (status, None, None),
+            }
+        };
+
+        ScanResult {
+            host: host.to_string(),
+            hostnames: Vec::new(),
+            tags: BTreeMap::new(),
+            port,
+            status,
+            registered_service: registered_service(port),
+            service,
+            version,
+            retries,
+        }
+    }
+}
+
+/// Counts a probe task as in flight until it is dropped, even by a panic
+struct InFlight(Arc<ProbeContext>);
+
+impl InFlight {
+    fn new(ctx: Arc<ProbeContext>) -> Self {
+        ctx.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(ctx)
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.0.completed.notify_waiters();
+    }
+}
+
+/// Results of a running scan, yielded as each host/port pair completes
+pub struct ScanStream {
+    results: Receiver<ScanResult>,
+    handle: Option<JoinHandle<()>>,
+    rate: Arc<RateLimiter>,
+    stop: StopHandle,
+}
+
+impl ScanStream {
+    /// Connection attempts made so far, including retries and service detection
+    pub fn probes_sent(&self) -> u64 {
+        self.rate.sent()
+    }
+
+    /// Handle that can stop this scan from another thread, e.g. a signal handler
+    pub fn stop_handle(&self) -> StopHandle {
+        self.stop.clone()
+    }
+
+    /// Whether the scan was asked to stop before covering every pair
+    pub fn was_stopped(&self) -> bool {
+        self.stop.is_stopped()
+    }
+}
+
+/// Asks a running scan to stop early.
+///
+/// No new connections are scheduled once stopped; probes already connecting finish
+/// normally and every pair that was never attempted is still yielded by the
+/// [`ScanStream`], with [`PortState::NotAttempted`].
+#[derive(Debug, Clone, Default)]
+pub struct StopHandle {
+    inner: Arc<StopSignal>,
+}
+
+#[derive(Debug, Default)]
+struct StopSignal {
+    stopped: AtomicBool,
+    notify: Notify,
+}
+
+impl StopHandle {
+    pub fn stop(&self) {
+        self.inner.stopped.store(true, Ordering::SeqCst);
+        self.inner.notify.notify_waiters();
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.inner.stopped.load(Ordering::SeqCst)
+    }
+
+    async fn stopped(&self) {
+        loop {
+            let notified = self.inner.notify.notified();
+            if self.is_stopped() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
+
+fn registered_service(port: u16) -> Option<String> {
+    ports::service_name(port, Protocol::Tcp).map(str::to_string)
+}
+
+fn skipped(host: IpAddr, port: u16) -> ScanResult {
+    ScanResult {
+        status: PortState::Skipped,
+        ..not_attempted(host, port)
+    }
+}
+
+fn not_attempted(host: IpAddr, port: u16) -> ScanResult {
+    ScanResult {
+        host: host.to_string(),
+        hostnames: Vec::new(),
+        tags: BTreeMap::new(),
+        port,
+        status: PortState::NotAttempted,
+        registered_service: registered_service(port),
+        service: None,
+        version: None,
+        retries: 0,
+    }
+}
+
+impl Drop for ScanStream {
+    /// Stop scheduling new probes when the caller stops listening early; probes
+    /// already connecting still finish in the background
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            self.stop.stop();
+        }
+    }
+}
+
+impl Iterator for ScanStream {
+    type Item = ScanResult;
+
+    fn next(&mut self) -> Option<ScanResult> {
+        match self.results.blocking_recv() {
+            Some(result) => Some(result),
+            None => {
+                // All senders are gone, so the scan has finished
+                if let Some(handle) = self.handle.take() {
+                    handle.join().unwrap();
+                }
+                None
+            }
+        }
+    }
+}
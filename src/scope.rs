@@ -0,0 +1,199 @@
+use crate::network::{parse_target, parse_target_list, split_target_list, TargetSet};
+use chrono::{DateTime, Datelike, Local, Timelike};
+use std::fs;
+use std::net::IpAddr;
+
+const DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+
+/// What an engagement allows to be scanned, read from a scope file.
+///
+/// A target is in scope when no `deny` entry covers it and, if there are any
+/// `allow` entries, one of them covers its address or one of its hostnames. Scans
+/// may only run inside the `window` entries, when there are any.
+#[derive(Debug, Clone, Default)]
+pub struct Scope {
+    allow: TargetSet,
+    allow_domains: Vec<String>,
+    deny: TargetSet,
+    windows: Vec<TimeWindow>,
+}
+
+/// Days of the week and a local time span during which scanning is allowed; a span
+/// ending before it starts runs past midnight into the next day
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimeWindow {
+    /// Monday first
+    days: [bool; 7],
+    /// Minutes after midnight
+    start: u32,
+    end: u32,
+}
+
+impl TimeWindow {
+    fn contains(&self, now: &DateTime<Local>) -> bool {
+        let today = now.weekday().num_days_from_monday() as usize;
+        let yesterday = (today + 6) % 7;
+        let minute = now.hour() * 60 + now.minute();
+        if self.start < self.end {
+            self.days[today] && self.start <= minute && minute < self.end
+        } else {
+            (self.days[today] && minute >= self.start) || (self.days[yesterday] && minute < self.end)
+        }
+    }
+}
+
+impl Scope {
+    /// Whether `ip`, which resolved from `hostnames`, may be scanned
+    pub fn permits(&self, ip: &IpAddr, hostnames: &[String]) -> bool {
+        if self.deny.covers(ip) {
+            return false;
+        }
+        !self.has_allow_list()
+            || self.allow.covers(ip)
+            || hostnames.iter().any(|hostname| self.allows_domain(hostname))
+    }
+
+    /// Addresses of `targets` that may not be scanned, judging hostnames as recorded
+    /// in `targets`
+    pub fn out_of_scope(&self, targets: &TargetSet) -> TargetSet {
+        if !self.has_allow_list() {
+            return targets.intersection(&self.deny);
+        }
+        let mut permitted = targets.intersection(&self.allow);
+        for ip in targets.named_addresses() {
+            if targets.hostnames(&ip).iter().any(|hostname| self.allows_domain(hostname)) {
+                permitted.add_ip(ip);
+            }
+        }
+        targets.difference(&permitted.difference(&self.deny))
+    }
+
+    /// Whether scanning is allowed at `now`
+    pub fn window_open(&self, now: &DateTime<Local>) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|window| window.contains(now))
+    }
+
+    /// Whether the scope restricts when scans may run
+    pub fn has_windows(&self) -> bool {
+        !self.windows.is_empty()
+    }
+
+    fn has_allow_list(&self) -> bool {
+        !self.allow.is_empty() || !self.allow_domains.is_empty()
+    }
+
+    /// `example.com` matches only itself, `*.example.com` any name below it
+    fn allows_domain(&self, hostname: &str) -> bool {
+        let hostname = hostname.trim_end_matches('.').to_ascii_lowercase();
+        self.allow_domains.iter().any(|domain| match domain.strip_prefix("*.") {
+            Some(parent) => hostname.ends_with(&format!(".{}", parent)),
+            None => hostname == *domain,
+        })
+    }
+}
+
+/// Read a scope file, see [`parse_scope`]
+pub fn load_scope(path: &str) -> Result<Scope, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read scope file {}: {}", path, e))?;
+    parse_scope(&contents, path)
+}
+
+/// Parse a scope file: one directive per line, `#` comments allowed.
+///
+/// - `allow TARGETS...`: IPs, CIDRs, ranges and patterns, or domains (`example.com`,
+///   `*.example.com`) matched against the hostnames targets were resolved from
+/// - `deny TARGETS...`: never scanned, even when allowed
+/// - `window DAYS HH:MM-HH:MM`: local times scans may run, e.g. `window mon-fri 09:00-17:30`;
+///   `DAYS` is `daily`, a day, a range or a comma-separated list of those
+pub fn parse_scope(contents: &str, source: &str) -> Result<Scope, String> {
+    let mut scope = Scope::default();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let error = |e: String| format!("{}:{}: {}", source, number + 1, e);
+        let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        if rest.is_empty() {
+            return Err(error(format!("\"{}\" needs a value", directive)));
+        }
+        match directive.to_ascii_lowercase().as_str() {
+            "allow" => {
+                for entry in split_target_list(rest) {
+                    if is_domain(&entry) {
+                        scope.allow_domains.push(entry.trim_end_matches('.').to_ascii_lowercase());
+                    } else {
+                        scope.allow.add(parse_target(&entry).map_err(error)?);
+                    }
+                }
+            }
+            "deny" => scope.deny.extend(parse_target_list(rest).map_err(error)?),
+            "window" => scope.windows.push(parse_window(rest).map_err(error)?),
+            other => return Err(error(format!("unknown directive \"{}\" (use allow, deny or window)", other))),
+        }
+    }
+    Ok(scope)
+}
+
+/// A name with at least one letter, optionally `*.`-prefixed; address patterns have none
+fn is_domain(entry: &str) -> bool {
+    let name = entry.strip_prefix("*.").unwrap_or(entry).trim_end_matches('.');
+    !name.contains(':')
+        && name.chars().any(|c| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-')
+}
+
+fn parse_window(window: &str) -> Result<TimeWindow, String> {
+    let (days, span) = window.split_once(char::is_whitespace)
+        .ok_or_else(|| format!("Invalid window \"{}\": expected DAYS HH:MM-HH:MM", window))?;
+    let (start, end) = span.trim().split_once('-')
+        .ok_or_else(|| format!("Invalid time span \"{}\": expected HH:MM-HH:MM", span.trim()))?;
+    let (start, end) = (parse_time(start)?, parse_time(end)?);
+    if start == end {
+        return Err(format!("Empty time span \"{}\"", span.trim()));
+    }
+    Ok(TimeWindow { days: parse_days(days)?, start, end })
+}
+
+fn parse_days(days: &str) -> Result<[bool; 7], String> {
+    let days = days.to_ascii_lowercase();
+    if days == "daily" || days == "*" {
+        return Ok([true; 7]);
+    }
+    let day = |name: &str| {
+        DAY_NAMES.iter().position(|day| name.len() >= 3 && day.starts_with(name))
+            .ok_or_else(|| format!("Unknown day \"{}\" (use mon, tue, wed, thu, fri, sat, sun or daily)", name))
+    };
+    let mut selected = [false; 7];
+    for item in days.split(',') {
+        match item.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (day(first)?, day(last)?);
+                // Ranges may wrap around the weekend, e.g. fri-mon
+                let mut current = first;
+                loop {
+                    selected[current] = true;
+                    if current == last {
+                        break;
+                    }
+                    current = (current + 1) % 7;
+                }
+            }
+            None => selected[day(item)?] = true,
+        }
+    }
+    Ok(selected)
+}
+
+/// `HH:MM` as minutes after midnight; `24:00` is the end of the day
+fn parse_time(time: &str) -> Result<u32, String> {
+    let invalid = || format!("Invalid time \"{}\": expected HH:MM", time);
+    let (hours, minutes) = time.trim().split_once(':').ok_or_else(invalid)?;
+    let (hours, minutes): (u32, u32) = (hours.parse().map_err(|_| invalid())?, minutes.parse().map_err(|_| invalid())?);
+    if minutes >= 60 || hours > 24 || (hours == 24 && minutes > 0) {
+        return Err(invalid());
+    }
+    Ok(hours * 60 + minutes)
+}
@@ -1,19 +1,38 @@
-use std::net::{TcpStream, SocketAddr};
-use std::io::{Read, Write};
+use crate::rate::RateLimiter;
+use std::net::SocketAddr;
 use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout as with_timeout;
 
-/// Attempt to detect service and version by banner grabbing
-pub fn detect_service(addr: &SocketAddr) -> (Option<String>, Option<String>) {
-    let mut stream = match TcpStream::connect_timeout(addr, Duration::from_secs(2)) {
-        Ok(s) => s,
-        Err(_) => return (None, None),
+/// Banner-grab the service name and version at `addr`, `(None, None)` if nothing answers, by running [`detect_service_async`] on a private runtime, so never from async code
+pub fn detect_service(
+    addr: &SocketAddr,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+) -> (Option<String>, Option<String>) {
+    match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime.block_on(detect_service_async(addr, connect_timeout, read_timeout, &RateLimiter::unlimited())),
+        Err(_) => (None, None),
+    }
+}
+
+/// Non-blocking version of [`detect_service`] used by the scanning engine. Every
+/// connection it opens first takes a token from `rate`.
+pub async fn detect_service_async(
+    addr: &SocketAddr,
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    rate: &RateLimiter,
+) -> (Option<String>, Option<String>) {
+    let mut stream = match connect(addr, connect_timeout, rate).await {
+        Some(s) => s,
+        None => return (None, None),
     };
 
-    stream.set_read_timeout(Some(Duration::from_secs(2))).ok();
-    
     // Try to read banner
     let mut buffer = [0u8; 1024];
-    let bytes_read = stream.read(&mut buffer).unwrap_or(0);
+    let bytes_read = read(&mut stream, &mut buffer, read_timeout).await;
     
     if bytes_read > 0 {
         let banner = String::from_utf8_lossy(&buffer[..bytes_read]);
@@ -30,21 +49,17 @@ pub fn detect_service(addr: &SocketAddr) -> (Option<String>, Option<String>) {
 
     for (service_name, probe) in probes {
         // Create a new connection for each probe
-        if let Ok(mut probe_stream) = TcpStream::connect_timeout(addr, Duration::from_secs(2)) {
-            probe_stream.set_read_timeout(Some(Duration::from_secs(2))).ok();
-            if let Ok(_) = probe_stream.write_all(&probe) {
-                if let Ok(_) = probe_stream.flush() {
-                    let mut response = [0u8; 512];
-                    if let Ok(n) = probe_stream.read(&mut response) {
-                        if n > 0 {
-                            let response_str = String::from_utf8_lossy(&response[..n]);
-                            let (service, version) = parse_banner(&response_str);
-                            if service != "Unknown" {
-                                return (Some(service), version);
-                            }
-                            return (Some(service_name.to_string()), None);
-                        }
+        if let Some(mut probe_stream) = connect(addr, connect_timeout, rate).await {
+            if probe_stream.write_all(&probe).await.is_ok() && probe_stream.flush().await.is_ok() {
+                let mut response = [0u8; 512];
+                let n = read(&mut probe_stream, &mut response, read_timeout).await;
+                if n > 0 {
+                    let response_str = String::from_utf8_lossy(&response[..n]);
+                    let (service, version) = parse_banner(&response_str);
+                    if service != "Unknown" {
+                        return (Some(service), version);
                     }
+                    return (Some(service_name.to_string()), None);
                 }
             }
         }
@@ -53,6 +68,22 @@ pub fn detect_service(addr: &SocketAddr) -> (Option<String>, Option<String>) {
     (None, None)
 }
 
+async fn connect(addr: &SocketAddr, timeout: Duration, rate: &RateLimiter) -> Option<TcpStream> {
+    rate.acquire().await;
+    match with_timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => Some(stream),
+        _ => None,
+    }
+}
+
+/// Read once into `buffer`, returning 0 on error, EOF or timeout
+async fn read(stream: &mut TcpStream, buffer: &mut [u8], timeout: Duration) -> usize {
+    match with_timeout(timeout, stream.read(buffer)).await {
+        Ok(Ok(n)) => n,
+        _ => 0,
+    }
+}
+
 fn parse_banner(banner: &str) -> (String, Option<String>) {
     let banner_lower = banner.to_lowercase();
     
@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Lower bound for an adaptive connect timeout, so a burst of very fast LAN
+/// answers cannot shrink the timeout below what a busy host needs
+pub const MIN_RTT_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// Smoothed round-trip time estimate (RFC 6298 style), fed with the time each
+/// connection attempt took to get an answer (handshake or reset)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RttEstimator {
+    srtt: Option<f64>,
+    rttvar: f64,
+}
+
+impl RttEstimator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold in one measured round trip
+    pub fn update(&mut self, sample: Duration) {
+        let r = sample.as_secs_f64();
+        match self.srtt {
+            None => {
+                self.srtt = Some(r);
+                self.rttvar = r / 2.0;
+            }
+            Some(srtt) => {
+                self.rttvar = 0.75 * self.rttvar + 0.25 * (srtt - r).abs();
+                self.srtt = Some(0.875 * srtt + 0.125 * r);
+            }
+        }
+    }
+
+    /// Current smoothed round trip, if any sample has been recorded
+    pub fn srtt(&self) -> Option<Duration> {
+        self.srtt.map(Duration::from_secs_f64)
+    }
+
+    /// `srtt + 4 * rttvar` clamped to `[min, max]`, or `None` before the first sample
+    pub fn timeout(&self, min: Duration, max: Duration) -> Option<Duration> {
+        self.srtt.map(|srtt| {
+            Duration::from_secs_f64(srtt + 4.0 * self.rttvar).clamp(min, max.max(min))
+        })
+    }
+}
+
+/// Per-host connect timeouts learned from the answers seen so far.
+///
+/// Hosts that have not answered yet borrow the estimate built from every host, so a
+/// quiet address on a fast LAN is given up on quickly instead of waiting for the
+/// full configured timeout.
+#[derive(Debug, Clone)]
+pub struct AdaptiveTimeouts {
+    global: RttEstimator,
+    hosts: HashMap<IpAddr, RttEstimator>,
+    max: Duration,
+}
+
+impl AdaptiveTimeouts {
+    /// `max` is both the timeout used before any answer arrives and the upper bound
+    pub fn new(max: Duration) -> Self {
+        AdaptiveTimeouts {
+            global: RttEstimator::new(),
+            hosts: HashMap::new(),
+            max,
+        }
+    }
+
+    /// Record how long `host` took to answer a connection attempt
+    pub fn record(&mut self, host: IpAddr, rtt: Duration) {
+        self.global.update(rtt);
+        self.hosts.entry(host).or_default().update(rtt);
+    }
+
+    /// Connect timeout to use for the next probe against `host`
+    pub fn connect_timeout(&self, host: IpAddr) -> Duration {
+        self.hosts
+            .get(&host)
+            .and_then(|rtt| rtt.timeout(MIN_RTT_TIMEOUT, self.max))
+            .or_else(|| self.global.timeout(MIN_RTT_TIMEOUT, self.max))
+            .unwrap_or(self.max)
+    }
+}
+
+/// Per-host spacing between probes (`--scan-delay` / `--max-scan-delay`).
+///
+/// Every host starts at the configured delay. When a host that has already answered
+/// lets a probe time out, that is taken as a sign it is dropping packets and its delay
+/// doubles (from at least 5ms) up to the maximum.
+#[derive(Debug, Clone)]
+pub struct HostPacer {
+    scan_delay: Duration,
+    max_scan_delay: Duration,
+    hosts: HashMap<IpAddr, HostDelay>,
+}
+
+#[derive(Debug, Clone)]
+struct HostDelay {
+    delay: Duration,
+    next_slot: Option<Instant>,
+    answered: bool,
+}
+
+impl HostPacer {
+    /// `max_scan_delay` below `scan_delay` disables growth; both are capped at
+    /// [`MAX_DURATION`]
+    pub fn new(scan_delay: Duration, max_scan_delay: Duration) -> Self {
+        let scan_delay = scan_delay.min(MAX_DURATION);
+        HostPacer {
+            scan_delay,
+            max_scan_delay: max_scan_delay.min(MAX_DURATION).max(scan_delay),
+            hosts: HashMap::new(),
+        }
+    }
+
+    fn host(&mut self, host: IpAddr) -> &mut HostDelay {
+        let delay = self.scan_delay;
+        self.hosts.entry(host).or_insert(HostDelay {
+            delay,
+            next_slot: None,
+            answered: false,
+        })
+    }
+
+    /// Reserve the next probe slot for `host` and return when it starts
+    pub fn reserve(&mut self, host: IpAddr) -> Instant {
+        let now = Instant::now();
+        let state = self.host(host);
+        let slot = state.next_slot.map_or(now, |next| next.max(now));
+        state.next_slot = Some(slot.checked_add(state.delay).unwrap_or(slot));
+        slot
+    }
+
+    /// `host` answered a probe (handshake or reset)
+    pub fn record_answer(&mut self, host: IpAddr) {
+        self.host(host).answered = true;
+    }
+
+    /// A probe against `host` timed out; returns the new delay if it grew
+    pub fn record_timeout(&mut self, host: IpAddr) -> Option<Duration> {
+        let max = self.max_scan_delay;
+        let state = self.host(host);
+        if !state.answered || state.delay >= max {
+            return None;
+        }
+        state.delay = state.delay.saturating_mul(2).max(Duration::from_millis(5)).min(max);
+        Some(state.delay)
+    }
+}
+
+/// Longest duration accepted for a timeout or delay
+pub const MAX_DURATION: Duration = Duration::from_secs(24 * 3600);
+
+/// Parse a duration such as `250ms`, `2s` or `1m`, up to [`MAX_DURATION`]; a bare
+/// number is taken as milliseconds
+pub fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: f64 = number.parse()
+        .map_err(|_| format!("Invalid duration: {}", value))?;
+    let secs = match unit {
+        "" | "ms" => number / 1000.0,
+        "s" => number,
+        "m" => number * 60.0,
+        "h" => number * 3600.0,
+        _ => return Err(format!("Invalid duration unit in {} (use ms, s, m or h)", value)),
+    };
+    Duration::try_from_secs_f64(secs).ok()
+        .filter(|duration| *duration <= MAX_DURATION)
+        .ok_or_else(|| format!("Duration out of range: {} (at most 24h)", value))
+}
+
+/// Nmap-style timing template, from `-T0` (paranoid) to `-T5` (insane)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimingTemplate {
+    Paranoid,
+    Sneaky,
+    Polite,
+    #[default]
+    Normal,
+    Aggressive,
+    Insane,
+}
+
+/// Scan pacing settings bundled by a [`TimingTemplate`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TimingSettings {
+    pub concurrency: usize,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    pub max_retries: u32,
+    pub scan_delay: Duration,
+    pub adaptive_timeout: bool,
+}
+
+impl TimingTemplate {
+    pub fn settings(&self) -> TimingSettings {
+        let ms = Duration::from_millis;
+        let (concurrency, connect_timeout, read_timeout, max_retries, scan_delay, adaptive_timeout) = match self {
+            TimingTemplate::Paranoid => (1, ms(10_000), ms(10_000), 3, ms(300_000), false),
+            TimingTemplate::Sneaky => (1, ms(10_000), ms(5_000), 3, ms(15_000), false),
+            TimingTemplate::Polite => (10, ms(5_000), ms(3_000), 2, ms(400), false),
+            TimingTemplate::Normal => (1000, ms(3_000), ms(2_000), 0, ms(0), false),
+            TimingTemplate::Aggressive => (3000, ms(1_250), ms(1_000), 0, ms(0), true),
+            TimingTemplate::Insane => (5000, ms(300), ms(500), 0, ms(0), true),
+        };
+        TimingSettings {
+            concurrency,
+            connect_timeout,
+            read_timeout,
+            max_retries,
+            scan_delay,
+            adaptive_timeout,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TimingTemplate::Paranoid => "paranoid",
+            TimingTemplate::Sneaky => "sneaky",
+            TimingTemplate::Polite => "polite",
+            TimingTemplate::Normal => "normal",
+            TimingTemplate::Aggressive => "aggressive",
+            TimingTemplate::Insane => "insane",
+        }
+    }
+}
+
+impl FromStr for TimingTemplate {
+    type Err = String;
+
+    /// Accepts the template number (`0`-`5`) or its name
+    fn from_str(value: &str) -> Result<Self, String> {
+        match value.trim().to_lowercase().as_str() {
+            "0" | "paranoid" => Ok(TimingTemplate::Paranoid),
+            "1" | "sneaky" => Ok(TimingTemplate::Sneaky),
+            "2" | "polite" => Ok(TimingTemplate::Polite),
+            "3" | "normal" => Ok(TimingTemplate::Normal),
+            "4" | "aggressive" => Ok(TimingTemplate::Aggressive),
+            "5" | "insane" => Ok(TimingTemplate::Insane),
+            _ => Err(format!("Invalid timing template: {} (use 0-5 or paranoid, sneaky, polite, normal, aggressive, insane)", value)),
+        }
+    }
+}
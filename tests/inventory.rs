@@ -0,0 +1,136 @@
+use std::collections::BTreeMap;
+use std::net::IpAddr;
+
+use ruscan::inventory::{parse_inventory, Asset, ColumnMap, InventoryFormat, MAX_HOST_RANGE};
+
+fn parse(format: InventoryFormat, contents: &str) -> Vec<Asset> {
+    parse_inventory(format, contents, &ColumnMap::default()).unwrap()
+}
+
+fn parse_mapped(format: InventoryFormat, contents: &str, map: &str) -> Vec<Asset> {
+    parse_inventory(format, contents, &map.parse().unwrap()).unwrap()
+}
+
+fn ip(address: &str) -> Option<IpAddr> {
+    Some(address.parse().unwrap())
+}
+
+fn tags(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+    pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+}
+
+#[test]
+fn terraform_state_lists_managed_resources() {
+    let assets = parse(InventoryFormat::Terraform, include_str!("fixtures/inventory/terraform.tfstate"));
+    let addresses: Vec<_> = assets.iter().map(|asset| asset.address).collect();
+    assert_eq!(addresses, vec![ip("10.0.1.10"), ip("203.0.113.10")]);
+    assert_eq!(assets[0].tags, tags(&[("resource", "aws_instance.web[0]"), ("id", "i-0abc"), ("name", "web-0")]));
+}
+
+#[test]
+fn terraform_show_output_walks_child_modules() {
+    let assets = parse(InventoryFormat::Terraform, include_str!("fixtures/inventory/terraform-show.json"));
+    let found: Vec<_> = assets.iter().map(|asset| (asset.address, asset.tags["resource"].as_str())).collect();
+    assert_eq!(found, vec![
+        (ip("10.0.2.20"), "google_compute_instance.db"),
+        (ip("10.0.3.30"), "module.cache.hcloud_server.redis"),
+    ]);
+}
+
+#[test]
+fn ansible_ini_expands_ranges_and_nested_groups() {
+    let assets = parse(InventoryFormat::Ansible, include_str!("fixtures/inventory/hosts.ini"));
+    assert_eq!(assets.len(), 6);
+    assert_eq!(assets[0], Asset { address: ip("10.0.2.20"), hostname: None, tags: tags(&[("group", "db,prod")]) });
+    assert_eq!(assets[1], Asset {
+        address: ip("10.0.0.5"),
+        hostname: Some("bastion.example.com".to_string()),
+        tags: tags(&[("inventory_host", "bastion.example.com")]),
+    });
+    assert_eq!(assets[2].address, ip("10.0.2.21"));
+    let web: Vec<_> = assets[3..].iter().map(|asset| asset.hostname.as_deref().unwrap()).collect();
+    assert_eq!(web, vec!["web01.example.com", "web02.example.com", "web03.example.com"]);
+    assert!(assets[3..].iter().all(|asset| asset.address.is_none() && asset.tags["group"] == "prod,web"));
+}
+
+#[test]
+fn ansible_ranges_are_capped() {
+    let at_limit = format!("h[1:{}]", MAX_HOST_RANGE);
+    assert_eq!(parse(InventoryFormat::Ansible, &at_limit).len(), MAX_HOST_RANGE as usize);
+
+    let error = parse_inventory(InventoryFormat::Ansible, "[big]\nh[0:99999999]\n", &ColumnMap::default()).unwrap_err();
+    assert!(error.starts_with("line 2: "), "{}", error);
+    assert!(error.contains("more than 65536 hosts"), "{}", error);
+}
+
+#[test]
+fn ansible_yaml_inventories_are_rejected() {
+    let error = parse_inventory(InventoryFormat::Ansible, include_str!("fixtures/inventory/hosts.yml"), &ColumnMap::default()).unwrap_err();
+    assert!(error.contains("ansible-inventory -i FILE --list"), "{}", error);
+}
+
+#[test]
+fn ansible_inventory_list_inherits_parent_groups() {
+    let assets = parse(InventoryFormat::Ansible, include_str!("fixtures/inventory/ansible-list.json"));
+    assert_eq!(assets, vec![
+        Asset { address: ip("10.0.4.9"), hostname: None, tags: BTreeMap::new() },
+        Asset {
+            address: ip("10.0.4.1"),
+            hostname: Some("app1".to_string()),
+            tags: tags(&[("inventory_host", "app1"), ("group", "app,prod")]),
+        },
+    ]);
+}
+
+#[test]
+fn aws_instances_list_every_address() {
+    let assets = parse(InventoryFormat::Aws, include_str!("fixtures/inventory/aws.json"));
+    let addresses: Vec<_> = assets.iter().map(|asset| asset.address).collect();
+    assert_eq!(addresses, vec![ip("10.0.5.1"), ip("10.0.5.2"), ip("198.51.100.7"), ip("2001:db8::5")]);
+    assert_eq!(assets[0].tags, tags(&[
+        ("instance_id", "i-0123"),
+        ("name", "api"),
+        ("state", "running"),
+        ("tag:Team", "payments"),
+    ]));
+}
+
+#[test]
+fn csv_columns_follow_the_map() {
+    let contents = include_str!("fixtures/inventory/assets.csv");
+    let assets = parse_mapped(InventoryFormat::Csv, contents, "address=IP,hostname=Name,owner=Owner");
+    assert_eq!(assets, vec![
+        Asset { address: ip("10.0.6.1"), hostname: Some("api".to_string()), tags: tags(&[("owner", "alice")]) },
+        Asset { address: None, hostname: Some("batch".to_string()), tags: tags(&[("owner", "bob")]) },
+        Asset { address: None, hostname: Some("files".to_string()), tags: tags(&[("owner", "carol")]) },
+    ]);
+
+    // Without tag entries every other column is a tag, and a name in the address column is kept
+    let assets = parse(InventoryFormat::Csv, contents);
+    assert_eq!(assets[0].address, ip("10.0.6.1"));
+    assert_eq!(assets[0].tags, tags(&[("Name", "api"), ("Owner", "alice"), ("Env", "prod")]));
+    assert_eq!(assets[2].hostname.as_deref(), Some("files.example.com"));
+}
+
+#[test]
+fn json_fields_accept_dotted_paths() {
+    let assets = parse_mapped(InventoryFormat::Json, include_str!("fixtures/inventory/assets.json"), "address=net.ip,hostname=name,owner=meta.owner");
+    assert_eq!(assets, vec![
+        Asset { address: ip("10.0.7.1"), hostname: Some("api".to_string()), tags: tags(&[("owner", "alice")]) },
+        Asset { address: None, hostname: Some("worker".to_string()), tags: tags(&[("owner", "bob")]) },
+    ]);
+}
+
+#[test]
+fn malformed_inventories_are_rejected() {
+    let error = |format: InventoryFormat, contents: &str| parse_inventory(format, contents, &ColumnMap::default()).unwrap_err();
+    assert!(error(InventoryFormat::Terraform, "{\"resources\": [").starts_with("Invalid JSON"));
+    assert!(error(InventoryFormat::Terraform, "{}").starts_with("Not a Terraform state"));
+    assert!(error(InventoryFormat::Aws, "{\"Volumes\": []}").starts_with("Not describe-instances output"));
+    assert!(error(InventoryFormat::Ansible, "[web:hostvars]\nweb1\n").contains("unknown section type"));
+    assert!(error(InventoryFormat::Ansible, "web[1:3.example.com\n").contains("unclosed range"));
+    assert!(error(InventoryFormat::Ansible, "web[3:1]\n").contains("unsupported range"));
+    assert!(error(InventoryFormat::Csv, "ip,name\n10.0.0.1,a,extra\n").starts_with("line 2"));
+    assert!(error(InventoryFormat::Json, "42").contains("array of objects"));
+    assert!("address".parse::<ColumnMap>().is_err());
+}
@@ -0,0 +1,91 @@
+use std::net::{IpAddr, Ipv6Addr, TcpListener};
+
+use ruscan::network::{parse_cidr, parse_target, TargetSet, TargetSpec};
+use ruscan::{PortState, ScanResult, Scanner};
+
+/// A port on `::1` that is listening, and one that was just released
+fn loopback_ports() -> (TcpListener, u16, u16) {
+    let listener = TcpListener::bind("[::1]:0").expect("IPv6 loopback is unavailable");
+    let open = listener.local_addr().unwrap().port();
+    let closed = TcpListener::bind("[::1]:0").unwrap().local_addr().unwrap().port();
+    (listener, open, closed)
+}
+
+fn status_of(results: &[ScanResult], port: u16) -> PortState {
+    results.iter().find(|result| result.port == port).expect("port was not scanned").status.clone()
+}
+
+#[test]
+fn scans_ipv6_loopback() {
+    let (_listener, open, closed) = loopback_ports();
+    let results = Scanner::new()
+        .target(IpAddr::V6(Ipv6Addr::LOCALHOST))
+        .ports([open, closed])
+        .scan();
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.host == "::1"));
+    assert_eq!(status_of(&results, open), PortState::Open);
+    assert_eq!(status_of(&results, closed), PortState::Closed);
+}
+
+#[test]
+fn scans_ipv6_host_port_pairs() {
+    let (_listener, open, _) = loopback_ports();
+    let results = Scanner::new()
+        .pair(IpAddr::V6(Ipv6Addr::LOCALHOST), open)
+        .scan();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(status_of(&results, open), PortState::Open);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn scans_scoped_ipv6_address() {
+    let (_listener, open, closed) = loopback_ports();
+    let mut targets = TargetSet::new();
+    targets.add(parse_target("::1%lo").unwrap());
+    let results = Scanner::new().targets(targets).ports([open, closed]).scan();
+
+    assert!(results.iter().all(|result| result.host == "::1%lo"));
+    assert_eq!(status_of(&results, open), PortState::Open);
+    assert_eq!(status_of(&results, closed), PortState::Closed);
+}
+
+#[test]
+fn parses_zone_ids() {
+    match parse_target("fe80::1%7").unwrap() {
+        TargetSpec::Scoped(ip, zone) => {
+            assert_eq!(ip, "fe80::1".parse::<Ipv6Addr>().unwrap());
+            assert_eq!(zone.id, 7);
+        }
+        spec => panic!("expected a scoped address, got {:?}", spec),
+    }
+    assert_eq!(parse_target("fe80::1%7").unwrap().to_string(), "fe80::1%7");
+    assert!(parse_target("10.0.0.1%7").is_err());
+    assert!(parse_target("fe80::1%").is_err());
+    assert!(parse_target("fe80::1%no-such-interface0").is_err());
+}
+
+#[test]
+fn counts_the_whole_ipv6_space() {
+    let mut targets = TargetSet::new();
+    targets.add(parse_cidr("::/0").unwrap());
+    assert_eq!(targets.len(), u128::MAX);
+    assert_eq!(targets.get(0), Some(IpAddr::V6(Ipv6Addr::UNSPECIFIED)));
+    assert_eq!(targets.iter().nth(1), Some(IpAddr::V6(Ipv6Addr::LOCALHOST)));
+
+    let plan = Scanner::new().targets(targets).ports([80]).plan();
+    assert!(plan.probes() > 0);
+}
+
+#[test]
+fn plans_the_whole_ipv6_space_on_several_ports() {
+    let mut targets = TargetSet::new();
+    targets.add(parse_cidr("::/0").unwrap());
+    let scanner = Scanner::new().targets(targets).ports([80, 443]);
+    assert_eq!(scanner.total_probes(), u128::MAX);
+    assert_eq!(scanner.extra_pair_count(), 0);
+    assert!(scanner.plan().probes() > 0);
+}
@@ -0,0 +1,34 @@
+use std::collections::HashSet;
+
+use ruscan::order::{CyclicPermutation, MAX_PERMUTED};
+
+/// Every index of `0..len` visited by the permutation, in order
+fn visit(len: u128, seed: u64) -> Vec<u128> {
+    CyclicPermutation::new(len, seed).expect("small spaces are permuted").collect()
+}
+
+#[test]
+fn permutation_visits_every_index_once() {
+    for len in [0, 1, 2, 3, 5, 7, 10, 11, 64, 97, 100, 255, 256, 1000, 1023] {
+        for seed in [0, 1, 42, u64::MAX] {
+            let order = visit(len, seed);
+            assert_eq!(order.len() as u128, len, "len {} seed {}", len, seed);
+            let distinct: HashSet<u128> = order.iter().copied().collect();
+            assert_eq!(distinct.len() as u128, len, "len {} seed {}", len, seed);
+            assert!(order.iter().all(|&index| index < len), "len {} seed {}", len, seed);
+        }
+    }
+}
+
+#[test]
+fn permutation_is_reproducible_from_its_seed() {
+    assert_eq!(visit(1000, 7), visit(1000, 7));
+    assert_ne!(visit(1000, 7), visit(1000, 8));
+    assert_ne!(visit(1000, 7), (0..1000).collect::<Vec<u128>>());
+}
+
+#[test]
+fn permutation_refuses_oversized_spaces() {
+    assert!(CyclicPermutation::new(MAX_PERMUTED, 1).is_some());
+    assert!(CyclicPermutation::new(MAX_PERMUTED + 1, 1).is_none());
+}
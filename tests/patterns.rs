@@ -0,0 +1,66 @@
+use std::net::IpAddr;
+
+use ruscan::network::{parse_target, parse_target_list, TargetSet, TargetSpec};
+
+fn addresses(pattern: &str) -> Vec<String> {
+    let mut targets = TargetSet::new();
+    targets.add(parse_target(pattern).unwrap());
+    targets.iter().map(|ip| ip.to_string()).collect()
+}
+
+#[test]
+fn wildcard_octet_covers_every_value() {
+    let spec = parse_target("10.0.0.*").unwrap();
+    assert!(matches!(spec, TargetSpec::Pattern(_)));
+    assert_eq!(spec.len(), 256);
+    let all = addresses("10.0.0.*");
+    assert_eq!(all.first().map(String::as_str), Some("10.0.0.0"));
+    assert_eq!(all.last().map(String::as_str), Some("10.0.0.255"));
+}
+
+#[test]
+fn octet_ranges_and_lists_combine() {
+    assert_eq!(addresses("192.168.1-2.1,3"), ["192.168.1.1", "192.168.1.3", "192.168.2.1", "192.168.2.3"]);
+    assert_eq!(addresses("10.0.0.1-3,7"), ["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.7"]);
+    // Overlapping values are produced once
+    assert_eq!(addresses("10.0.0.1-3,2-4"), ["10.0.0.1", "10.0.0.2", "10.0.0.3", "10.0.0.4"]);
+    // Open-ended ranges run to the octet's bounds
+    assert_eq!(parse_target("10.0.0.250-").unwrap().len(), 6);
+    assert_eq!(parse_target("10.0.0.-5").unwrap().len(), 6);
+}
+
+#[test]
+fn a_pattern_naming_one_address_is_a_single_target() {
+    let ip: IpAddr = "10.0.0.5".parse().unwrap();
+    assert_eq!(parse_target("10.0.0.5-5").unwrap(), TargetSpec::Single(ip));
+}
+
+#[test]
+fn target_lists_keep_octet_value_lists_together() {
+    let targets = parse_target_list("10.0.0,2.1-5, 192.168.1.1").unwrap();
+    assert_eq!(targets.len(), 11);
+}
+
+#[test]
+fn malformed_octets_are_rejected() {
+    for pattern in [
+        "10.0.0.256",
+        "10.0.0.300-310",
+        "10.0.0.5-3",
+        "10.0.0.1,,2",
+        "10.0.0",
+        "10.0.0.0.1",
+        "10.0.x.1",
+        "10.0.0.-1-2",
+    ] {
+        assert!(parse_target(pattern).is_err(), "{} was accepted", pattern);
+    }
+}
+
+#[test]
+fn malformed_octet_errors_name_the_problem() {
+    let error = parse_target("10.0.0.256").unwrap_err();
+    assert!(error.contains("not a number between 0 and 255"), "{}", error);
+    let error = parse_target("10.0.0.5-3").unwrap_err();
+    assert!(error.contains("reversed"), "{}", error);
+}
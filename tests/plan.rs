@@ -0,0 +1,51 @@
+use ruscan::network::{parse_cidr, parse_target, TargetSet};
+use ruscan::scanner::Scanner;
+
+fn targets(specs: &[&str]) -> TargetSet {
+    let mut targets = TargetSet::new();
+    for spec in specs {
+        targets.add(parse_target(spec).unwrap());
+    }
+    targets
+}
+
+#[test]
+fn plan_grid_matches_the_probes_when_nothing_is_skipped() {
+    let mut cidr = TargetSet::new();
+    cidr.add(parse_cidr("10.0.0.0/29").unwrap());
+    let plan = Scanner::new().targets(cidr).ports([80, 443]).plan();
+    assert_eq!(plan.skipped, 0);
+    assert_eq!(plan.hosts, 6);
+    assert_eq!(plan.targets.len() * plan.ports.len() as u128, plan.probes());
+}
+
+#[test]
+fn plan_leaves_out_excluded_hosts_and_ports() {
+    let mut cidr = TargetSet::new();
+    cidr.add(parse_cidr("10.0.0.0/29").unwrap());
+    let plan = Scanner::new()
+        .targets(cidr)
+        .ports([80, 443])
+        .exclude(targets(&["10.0.0.2"]))
+        .exclude_ports([443])
+        .plan();
+    assert_eq!(plan.targets.len(), 5);
+    assert!(!plan.targets.contains(&"10.0.0.2".parse().unwrap()));
+    assert_eq!(plan.ports, vec![80]);
+    assert_eq!(plan.excluded_ports, vec![443]);
+    assert_eq!(plan.targets.len() * plan.ports.len() as u128, plan.probes());
+    assert!(plan.to_text(None).starts_with("Hosts:        5\nPorts:        1 (1 excluded)\n"));
+}
+
+#[test]
+fn plan_does_not_count_skipped_special_addresses() {
+    let plan = Scanner::new()
+        .targets(targets(&["10.0.0.0-10.0.0.255", "192.0.2.1"]))
+        .ports([80])
+        .plan();
+    assert!(!plan.targets.contains(&"192.0.2.1".parse().unwrap()));
+    assert_eq!(plan.targets.len(), 256);
+    assert_eq!(plan.hosts, 254);
+    assert_eq!(plan.probes(), 254);
+    assert_eq!(plan.skipped, 3);
+}
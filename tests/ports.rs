@@ -0,0 +1,64 @@
+use ruscan::ports::{parse_port_spec, top_ports, PortSpecError, Protocol};
+
+fn tcp(spec: &str) -> Vec<u16> {
+    parse_port_spec(spec).unwrap().tcp().collect()
+}
+
+fn udp(spec: &str) -> Vec<u16> {
+    parse_port_spec(spec).unwrap().udp().collect()
+}
+
+#[test]
+fn dash_means_every_port() {
+    let all = tcp("-");
+    assert_eq!(all.len(), 65535);
+    assert_eq!(all.first(), Some(&1));
+    assert_eq!(all.last(), Some(&65535));
+}
+
+#[test]
+fn open_ended_ranges_run_to_the_bounds() {
+    assert_eq!(tcp("-3"), [1, 2, 3]);
+    assert_eq!(tcp("65533-"), [65533, 65534, 65535]);
+}
+
+#[test]
+fn lists_and_ranges_are_merged_and_sorted() {
+    assert_eq!(tcp("443, 80,22-24,23"), [22, 23, 24, 80, 443]);
+}
+
+#[test]
+fn protocol_prefixes_apply_until_the_next_prefix() {
+    let spec = "22,U:53,161,T:80";
+    assert_eq!(tcp(spec), [22, 80]);
+    assert_eq!(udp(spec), [53, 161]);
+    assert_eq!(udp("u:53"), [53]);
+}
+
+#[test]
+fn service_names_resolve_to_ports() {
+    assert_eq!(tcp("ssh,HTTP"), [22, 80]);
+}
+
+#[test]
+fn malformed_specs_are_rejected() {
+    assert_eq!(parse_port_spec("").unwrap_err(), PortSpecError::Empty);
+    assert_eq!(parse_port_spec(" , ,").unwrap_err(), PortSpecError::Empty);
+    assert_eq!(parse_port_spec("100-10").unwrap_err(), PortSpecError::ReversedRange("100-10".to_string()));
+    assert_eq!(parse_port_spec("no-such-service").unwrap_err(), PortSpecError::UnknownService("no-such-service".to_string()));
+    assert_eq!(parse_port_spec("X:80").unwrap_err(), PortSpecError::UnknownProtocol("X".to_string()));
+    assert!(matches!(parse_port_spec("65536"), Err(PortSpecError::InvalidPort(_))));
+    assert!(matches!(parse_port_spec("80-90-100"), Err(PortSpecError::InvalidPort(_))));
+}
+
+#[test]
+fn top_ports_rank_a_thousand_tcp_ports() {
+    let top = top_ports(1000, Protocol::Tcp);
+    assert_eq!(top.len(), 1000);
+    assert_eq!(&top[..3], &[80, 23, 443]);
+    let mut unique = top.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(unique.len(), 1000);
+    assert_eq!(top_ports(5000, Protocol::Tcp), top);
+}
@@ -0,0 +1,24 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use ruscan::rate::{parse_rate, MIN_RATE};
+use ruscan::{PortState, Scanner};
+
+#[test]
+fn rejects_rates_that_cannot_be_paced() {
+    assert_eq!(parse_rate("200").unwrap(), 200.0);
+    assert_eq!(parse_rate(&MIN_RATE.to_string()).unwrap(), MIN_RATE);
+    for rate in ["0", "-5", "NaN", "inf", "0.0000000000000000001", "fast"] {
+        assert!(parse_rate(rate).is_err(), "{} was accepted", rate);
+    }
+}
+
+#[test]
+fn tiny_max_rates_still_probe() {
+    let results = Scanner::new()
+        .target(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .port(1)
+        .max_rate(1e-19)
+        .scan();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status, PortState::Closed);
+}
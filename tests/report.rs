@@ -0,0 +1,64 @@
+use std::fs;
+
+use ruscan::{PortState, ReportGenerator, ScanResult};
+
+fn result(port: u16, status: PortState) -> ScanResult {
+    ScanResult {
+        host: "127.0.0.1".to_string(),
+        hostnames: Vec::new(),
+        tags: Default::default(),
+        port,
+        status,
+        registered_service: None,
+        service: None,
+        version: None,
+        retries: 0,
+    }
+}
+
+/// The JSON report as written to a file
+fn json_report(results: Vec<ScanResult>, name: &str) -> serde_json::Value {
+    let mut report = ReportGenerator::new();
+    for result in results {
+        report.add_result(result);
+    }
+    let path = std::env::temp_dir().join(format!("ruscan-{}-{}.json", name, std::process::id()));
+    report.generate("json", path.to_str()).unwrap();
+    let json = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    serde_json::from_str(&json).unwrap()
+}
+
+#[test]
+fn json_report_wraps_results_with_completeness() {
+    let report = json_report(vec![result(22, PortState::Open), result(23, PortState::Closed)], "complete");
+    assert_eq!(report["complete"], true);
+    let results = report["results"].as_array().expect("results is an array");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["port"], 22);
+    assert_eq!(results[0]["status"], "open");
+}
+
+#[test]
+fn json_report_of_an_interrupted_scan_is_incomplete() {
+    let report = json_report(vec![result(22, PortState::Open), result(23, PortState::NotAttempted)], "interrupted");
+    assert_eq!(report["complete"], false);
+    assert_eq!(report["results"][1]["status"], "not-attempted");
+}
+
+#[test]
+fn json_report_gives_errors_a_status_and_a_reason() {
+    let report = json_report(vec![result(25, PortState::Error("Too many open files".to_string()))], "error");
+    assert_eq!(report["results"][0]["status"], "error");
+    assert_eq!(report["results"][0]["reason"], "Too many open files");
+}
+
+#[test]
+fn states_survive_a_round_trip() {
+    for status in [PortState::Open, PortState::NotAttempted, PortState::Error("refused <here>".to_string())] {
+        let json = serde_json::to_string(&result(80, status.clone())).unwrap();
+        assert_eq!(json.contains("\"reason\""), matches!(status, PortState::Error(_)));
+        let parsed: ScanResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.status, status);
+    }
+}
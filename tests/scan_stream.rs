@@ -0,0 +1,26 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+use ruscan::Scanner;
+
+#[test]
+fn dropping_the_stream_stops_the_scan() {
+    let mut stream = Scanner::new()
+        .target(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .ports(1..=u16::MAX)
+        .concurrency(8)
+        .scan_stream();
+    let stop = stream.stop_handle();
+    assert!(stream.next().is_some());
+    drop(stream);
+    assert!(stop.is_stopped());
+}
+
+#[test]
+fn huge_concurrency_still_reports_every_pair() {
+    let results = Scanner::new()
+        .target(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .ports(1..=500)
+        .concurrency(usize::MAX)
+        .scan();
+    assert_eq!(results.len(), 500);
+}
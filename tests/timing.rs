@@ -0,0 +1,27 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::Duration;
+
+use ruscan::timing::{parse_duration, MAX_DURATION};
+use ruscan::{PortState, Scanner};
+
+#[test]
+fn parses_durations_up_to_the_cap() {
+    assert_eq!(parse_duration("250").unwrap(), Duration::from_millis(250));
+    assert_eq!(parse_duration("1.5s").unwrap(), Duration::from_millis(1500));
+    assert_eq!(parse_duration("24h").unwrap(), MAX_DURATION);
+    assert!(parse_duration("25h").is_err());
+    assert!(parse_duration("10000000000000000000s").is_err());
+    assert!(parse_duration("5d").is_err());
+}
+
+#[test]
+fn huge_scan_delays_do_not_break_the_scan() {
+    let results = Scanner::new()
+        .target(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .port(1)
+        .scan_delay(Duration::MAX)
+        .max_scan_delay(Duration::MAX)
+        .scan();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status, PortState::Closed);
+}